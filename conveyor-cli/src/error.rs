@@ -0,0 +1,21 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum PipelineError {
+    UnknownStation(String),
+    InvalidOptions(String, String),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PipelineError::UnknownStation(name) => write!(f, "unknown station: {}", name),
+            PipelineError::InvalidOptions(name, msg) => {
+                write!(f, "invalid options for station {}: {}", name, msg)
+            }
+        }
+    }
+}
+
+impl Error for PipelineError {}
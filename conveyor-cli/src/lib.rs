@@ -0,0 +1,12 @@
+#[macro_use]
+extern crate serde_derive;
+
+pub mod error;
+pub mod pipeline;
+pub mod registry;
+pub mod runner;
+
+pub use error::*;
+pub use pipeline::*;
+pub use registry::*;
+pub use runner::*;
@@ -0,0 +1,95 @@
+use clap::{crate_version, App, Arg};
+use conveyor_cli::{run, Pipeline, Registry, RunOptions};
+use std::path::PathBuf;
+use std::process;
+
+fn parse_number(matches: &clap::ArgMatches, name: &str) -> Option<usize> {
+    matches.value_of(name).map(|m| match m.parse() {
+        Ok(m) => m,
+        Err(_) => {
+            eprintln!("--{} expects a number, got {}", name, m);
+            process::exit(2);
+        }
+    })
+}
+
+#[tokio::main]
+async fn main() {
+    let matches = App::new("conveyor")
+        .version(crate_version!())
+        .about("Run declarative conveyor pipelines")
+        .arg(
+            Arg::with_name("PIPELINE")
+                .help("Path to the pipeline definition (json)")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Print the pipeline graph without running it"),
+        )
+        .arg(
+            Arg::with_name("limit")
+                .long("limit")
+                .value_name("N")
+                .takes_value(true)
+                .help("Only process the first N inputs"),
+        )
+        .arg(
+            Arg::with_name("concurrency")
+                .short("c")
+                .long("concurrency")
+                .value_name("N")
+                .takes_value(true)
+                .help("Number of items processed concurrently"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("DIR")
+                .takes_value(true)
+                .help("Write each result to DIR instead of printing it"),
+        )
+        .get_matches();
+
+    let pipeline = match Pipeline::from_path(matches.value_of("PIPELINE").unwrap()) {
+        Ok(pipeline) => pipeline,
+        Err(e) => {
+            eprintln!("could not load pipeline: {}", e);
+            process::exit(2);
+        }
+    };
+
+    let registry = Registry::default();
+
+    if matches.is_present("dry-run") {
+        println!("{}", pipeline);
+        if let Err(e) = registry.validate(&pipeline.stations) {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+        return;
+    }
+
+    let options = RunOptions {
+        concurrency: parse_number(&matches, "concurrency"),
+        limit: parse_number(&matches, "limit"),
+        output: matches.value_of("output").map(PathBuf::from),
+    };
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match run(pipeline, &registry, options, &mut out).await {
+        Ok(summary) => {
+            eprintln!("{} succeeded, {} failed", summary.succeeded, summary.failed);
+            if summary.failed > 0 {
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use conveyor::{ConveyorError, Result};
+use conveyor_work::http::HttpOptions;
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+fn default_concurrency() -> usize {
+    4
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ProducerDef {
+    Http { requests: Vec<HttpOptions> },
+    Glob { root: String, pattern: String },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StationDef {
+    pub name: String,
+    #[serde(default)]
+    pub options: Value,
+}
+
+#[derive(Deserialize)]
+pub struct Pipeline {
+    pub producer: ProducerDef,
+    #[serde(default)]
+    pub stations: Vec<StationDef>,
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+impl Pipeline {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Pipeline> {
+        let content = fs::read_to_string(path).map_err(ConveyorError::new)?;
+        content.parse()
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = ConveyorError;
+    fn from_str(input: &str) -> Result<Pipeline> {
+        serde_json::from_str(input).map_err(ConveyorError::new)
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.producer {
+            ProducerDef::Http { requests } => {
+                writeln!(f, "producer: http ({} requests)", requests.len())?;
+                for req in requests {
+                    writeln!(f, "  {:?} {}", req.method, req.url)?;
+                }
+            }
+            ProducerDef::Glob { root, pattern } => {
+                writeln!(f, "producer: glob {} in {}", pattern, root)?;
+            }
+        }
+        for station in &self.stations {
+            if station.options.is_null() {
+                writeln!(f, "  -> {}", station.name)?;
+            } else {
+                writeln!(f, "  -> {} {}", station.name, station.options)?;
+            }
+        }
+        writeln!(f, "concurrency: {}", self.concurrency)?;
+        match &self.output {
+            Some(output) => write!(f, "output: {}", output),
+            None => write!(f, "output: stdout"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pipeline() {
+        let pipeline: Pipeline = r#"{
+                "producer": {
+                    "type": "http",
+                    "requests": [
+                        { "method": "GET", "url": "http://localhost/" }
+                    ]
+                },
+                "stations": [
+                    { "name": "read" },
+                    { "name": "log" }
+                ]
+            }"#
+        .parse()
+        .unwrap();
+
+        assert_eq!(pipeline.concurrency, 4);
+        assert_eq!(pipeline.stations.len(), 2);
+        assert!(pipeline.output.is_none());
+        assert_eq!(
+            pipeline.to_string(),
+            "producer: http (1 requests)\n  GET http://localhost/\n  -> read\n  -> log\nconcurrency: 4\noutput: stdout"
+        );
+    }
+
+    #[test]
+    fn parse_glob_pipeline() {
+        let pipeline: Pipeline = r#"{
+                "producer": { "type": "glob", "root": "./", "pattern": "**/*.rs" },
+                "concurrency": 2,
+                "output": "out"
+            }"#
+        .parse()
+        .unwrap();
+
+        assert!(pipeline.stations.is_empty());
+        assert_eq!(
+            pipeline.to_string(),
+            "producer: glob **/*.rs in ./\nconcurrency: 2\noutput: out"
+        );
+    }
+}
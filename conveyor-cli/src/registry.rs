@@ -0,0 +1,132 @@
+use super::error::PipelineError;
+use super::pipeline::StationDef;
+use conveyor::{into_box, station_fn, ConveyorError, Result};
+use conveyor_work::package::Package;
+use conveyor_work::utils::BoxedStation;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub type StationFactory = Box<dyn Fn(&Value) -> Result<BoxedStation> + Send + Sync>;
+
+pub struct Registry {
+    stations: HashMap<String, StationFactory>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry {
+            stations: HashMap::new(),
+        }
+    }
+
+    pub fn register<S, F>(&mut self, name: S, factory: F) -> &mut Self
+    where
+        S: AsRef<str>,
+        F: Fn(&Value) -> Result<BoxedStation> + Send + Sync + 'static,
+    {
+        self.stations
+            .insert(name.as_ref().to_string(), Box::new(factory));
+        self
+    }
+
+    pub fn contains<S: AsRef<str>>(&self, name: S) -> bool {
+        self.stations.contains_key(name.as_ref())
+    }
+
+    pub fn build(&self, def: &StationDef) -> Result<BoxedStation> {
+        match self.stations.get(&def.name) {
+            Some(factory) => factory(&def.options),
+            None => Err(ConveyorError::new(PipelineError::UnknownStation(
+                def.name.clone(),
+            ))),
+        }
+    }
+
+    pub fn validate(&self, defs: &[StationDef]) -> Result<()> {
+        for def in defs {
+            if !self.contains(&def.name) {
+                return Err(ConveyorError::new(PipelineError::UnknownStation(
+                    def.name.clone(),
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        let mut registry = Registry::new();
+        registry
+            .register("identity", |_| {
+                Ok(into_box(station_fn(|p: Package| async move { Ok(p) })))
+            })
+            .register("read", |_| {
+                Ok(into_box(station_fn(|mut p: Package| async move {
+                    let content = p.read_content().await?;
+                    Ok(p.set_value(content))
+                })))
+            })
+            .register("log", |_| {
+                Ok(into_box(station_fn(|p: Package| async move {
+                    eprintln!("{}", p.name());
+                    Ok(p)
+                })))
+            })
+            .register("rename", |options| {
+                let prefix = options
+                    .get("prefix")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                let suffix = options
+                    .get("suffix")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                if prefix.is_empty() && suffix.is_empty() {
+                    return Err(ConveyorError::new(PipelineError::InvalidOptions(
+                        "rename".to_string(),
+                        "expected prefix or suffix".to_string(),
+                    )));
+                }
+                Ok(into_box(station_fn(move |p: Package| {
+                    let name = format!("{}{}{}", prefix, p.name(), suffix);
+                    conveyor::futures::future::ready(Ok(p.set_name(name)))
+                })))
+            });
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conveyor::futures::executor::block_on;
+    use serde_json::json;
+
+    #[test]
+    fn build_builtin() {
+        let registry = Registry::default();
+        let station = registry
+            .build(&StationDef {
+                name: "rename".to_string(),
+                options: json!({ "prefix": "pre-" }),
+            })
+            .unwrap();
+
+        let ret = block_on(station.execute(Package::new("test", "content"))).unwrap();
+        assert_eq!(ret.name(), "pre-test");
+    }
+
+    #[test]
+    fn unknown_station() {
+        let registry = Registry::default();
+        let defs = vec![StationDef {
+            name: "nope".to_string(),
+            options: Value::Null,
+        }];
+        assert!(registry.validate(&defs).is_err());
+        assert!(registry.build(&defs[0]).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use super::pipeline::{Pipeline, ProducerDef};
+use super::registry::Registry;
+use conveyor::futures::prelude::*;
+use conveyor::{ConcurrentStream, ConveyorError, Result};
+use conveyor_work::package::{Package, PackageContent};
+use conveyor_work::producers;
+use conveyor_work::traits::ChainStreamer;
+use conveyor_work::utils::BoxWrap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use vfs::physical::PhysicalFS;
+
+pub type PackageFuture = Pin<Box<dyn Future<Output = Result<Package>> + Send>>;
+pub type PackageStream = Pin<Box<dyn Stream<Item = PackageFuture> + Send>>;
+
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    pub concurrency: Option<usize>,
+    pub limit: Option<usize>,
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Summary {
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+fn producer(def: ProducerDef) -> Result<PackageStream> {
+    match def {
+        ProducerDef::Http { requests } => Ok(Box::pin(
+            producers::http(requests).map(|m| Box::pin(m) as PackageFuture),
+        )),
+        ProducerDef::Glob { root, pattern } => {
+            let fs = PhysicalFS::new(&root).map_err(ConveyorError::new)?;
+            Ok(Box::pin(
+                producers::fs::glob(fs, pattern).map(|m| Box::pin(m) as PackageFuture),
+            ))
+        }
+    }
+}
+
+/// Turn a package name (an url or a path) into a flat file name
+fn file_name(name: &str) -> String {
+    let name = match name.find("://") {
+        Some(idx) => &name[idx + 3..],
+        None => name,
+    };
+    let name = name
+        .trim_matches('/')
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    if name.is_empty() {
+        String::from("index")
+    } else {
+        name
+    }
+}
+
+/// Streamed content is written as it arrives, without buffering it
+async fn write_package(dir: &Path, package: Package) -> Result<PathBuf> {
+    let path = dir.join(file_name(package.name()));
+    match package.into_content() {
+        PackageContent::Stream(mut stream) => {
+            let mut file = fs::File::create(&path).map_err(ConveyorError::new)?;
+            while let Some(chunk) = stream.try_next().await? {
+                file.write_all(&chunk).map_err(ConveyorError::new)?;
+            }
+        }
+        content => {
+            let content = content.into_future().await?;
+            fs::write(&path, content).map_err(ConveyorError::new)?;
+        }
+    }
+    Ok(path)
+}
+
+pub fn build(pipeline: Pipeline, registry: &Registry, limit: Option<usize>) -> Result<PackageStream> {
+    registry.validate(&pipeline.stations)?;
+
+    let mut stream = producer(pipeline.producer)?;
+    if let Some(limit) = limit {
+        stream = Box::pin(stream.take(limit));
+    }
+
+    for def in &pipeline.stations {
+        let station = registry.build(def)?;
+        stream = Box::pin(stream.pipe(BoxWrap::new(station)));
+    }
+
+    Ok(stream)
+}
+
+pub async fn run<W: Write>(
+    pipeline: Pipeline,
+    registry: &Registry,
+    options: RunOptions,
+    out: &mut W,
+) -> Result<Summary> {
+    let concurrency = options.concurrency.unwrap_or(pipeline.concurrency);
+    let output = options
+        .output
+        .or_else(|| pipeline.output.as_ref().map(PathBuf::from));
+
+    if let Some(output) = &output {
+        fs::create_dir_all(output).map_err(ConveyorError::new)?;
+    }
+
+    let stream = build(pipeline, registry, options.limit)?;
+    let mut stream = ConcurrentStream::new(stream, concurrency);
+
+    let mut summary = Summary::default();
+
+    while let Some(next) = stream.next().await {
+        let ret = match next {
+            Ok(mut package) => match &output {
+                Some(dir) => {
+                    let name = package.name().to_string();
+                    write_package(dir, package)
+                        .await
+                        .map(|path| format!("ok {} -> {}", name, path.display()))
+                }
+                None => package
+                    .read_content()
+                    .await
+                    .map(|content| format!("ok {} ({} bytes)", package.name(), content.len())),
+            },
+            Err(e) => Err(e),
+        };
+
+        let line = match ret {
+            Ok(line) => {
+                summary.succeeded += 1;
+                line
+            }
+            Err(e) => {
+                summary.failed += 1;
+                format!("error {}", e)
+            }
+        };
+        writeln!(out, "{}", line).map_err(ConveyorError::new)?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conveyor_http::testing::{response, serve_fn};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "conveyor-cli-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("http://localhost:80/a/b"), "localhost_80_a_b");
+        assert_eq!(file_name("src/lib.rs"), "src_lib.rs");
+        assert_eq!(file_name("https://"), "index");
+    }
+
+    #[tokio::test]
+    async fn run_http_to_output() {
+        let url = serve_fn(|request| {
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+            response("200 OK", &[], path.as_bytes())
+        });
+        let addr = url.as_str().trim_end_matches('/').to_string();
+        let dir = temp_dir("http");
+        let pipeline: Pipeline = format!(
+            r#"{{
+                "producer": {{
+                    "type": "http",
+                    "requests": [
+                        {{ "method": "GET", "url": "{0}/first" }},
+                        {{ "method": "GET", "url": "{0}/second" }}
+                    ]
+                }},
+                "stations": [ {{ "name": "read" }} ]
+            }}"#,
+            addr
+        )
+        .parse()
+        .unwrap();
+
+        let mut out = Vec::new();
+        let summary = run(
+            pipeline,
+            &Registry::default(),
+            RunOptions {
+                output: Some(dir.clone()),
+                ..Default::default()
+            },
+            &mut out,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            summary,
+            Summary {
+                succeeded: 2,
+                failed: 0
+            }
+        );
+
+        let host = file_name(&addr);
+        let first = fs::read_to_string(dir.join(format!("{}_first", host))).unwrap();
+        assert_eq!(first, "/first");
+        let second = fs::read_to_string(dir.join(format!("{}_second", host))).unwrap();
+        assert_eq!(second, "/second");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn run_glob_with_limit() {
+        let dir = temp_dir("glob");
+        for i in 0..3 {
+            fs::write(dir.join(format!("{}.txt", i)), "content").unwrap();
+        }
+
+        let pipeline: Pipeline = format!(
+            r#"{{
+                "producer": {{ "type": "glob", "root": "{}", "pattern": "*.txt" }},
+                "stations": [ {{ "name": "rename", "options": {{ "suffix": ".bak" }} }} ]
+            }}"#,
+            dir.display()
+        )
+        .parse()
+        .unwrap();
+
+        let mut out = Vec::new();
+        let summary = run(
+            pipeline,
+            &Registry::default(),
+            RunOptions {
+                limit: Some(1),
+                ..Default::default()
+            },
+            &mut out,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.succeeded, 1);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("ok "));
+        assert!(out.trim_end().ends_with(".bak (7 bytes)"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
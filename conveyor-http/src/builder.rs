@@ -0,0 +1,221 @@
+use super::{ErrorForStatus, HeaderMap, HeaderName, HeaderValue, Http};
+use conveyor::{ConveyorError, Result};
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, ClientBuilder, Proxy};
+use std::convert::TryInto;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use reqwest::cookie::Jar as CookieJar;
+
+/// Configures the client of an `Http` station, see `Http::builder`
+pub struct HttpBuilder {
+    builder: ClientBuilder,
+    headers: HeaderMap,
+    status: Option<ErrorForStatus>,
+    error: Option<ConveyorError>,
+}
+
+impl HttpBuilder {
+    pub fn new() -> HttpBuilder {
+        HttpBuilder {
+            builder: ClientBuilder::new(),
+            headers: HeaderMap::new(),
+            status: None,
+            error: None,
+        }
+    }
+
+    fn map<F: FnOnce(ClientBuilder) -> ClientBuilder>(mut self, f: F) -> HttpBuilder {
+        self.builder = f(self.builder);
+        self
+    }
+
+    pub fn connect_timeout(self, timeout: Duration) -> HttpBuilder {
+        self.map(|m| m.connect_timeout(timeout))
+    }
+
+    /// How long to wait for each read of the response
+    pub fn read_timeout(self, timeout: Duration) -> HttpBuilder {
+        self.map(|m| m.read_timeout(timeout))
+    }
+
+    /// How long a whole request may take, up to the end of the body
+    pub fn timeout(self, timeout: Duration) -> HttpBuilder {
+        self.map(|m| m.timeout(timeout))
+    }
+
+    /// Follows up to `max` redirects, 10 by default. 0 doesn't follow any.
+    pub fn redirects(self, max: usize) -> HttpBuilder {
+        let policy = match max {
+            0 => Policy::none(),
+            max => Policy::limited(max),
+        };
+        self.map(|m| m.redirect(policy))
+    }
+
+    pub fn proxy(self, proxy: Proxy) -> HttpBuilder {
+        self.map(|m| m.proxy(proxy))
+    }
+
+    /// Trusts the PEM encoded certificate `pem` besides the system's
+    pub fn root_certificate(mut self, pem: &[u8]) -> HttpBuilder {
+        match Certificate::from_pem(pem) {
+            Ok(cert) => self.map(|m| m.add_root_certificate(cert)),
+            Err(e) => {
+                self.error.get_or_insert(ConveyorError::new(e));
+                self
+            }
+        }
+    }
+
+    /// Decodes gzip encoded bodies, on by default
+    pub fn gzip(self, enable: bool) -> HttpBuilder {
+        self.map(|m| m.gzip(enable))
+    }
+
+    /// Decodes brotli encoded bodies, on by default
+    pub fn brotli(self, enable: bool) -> HttpBuilder {
+        self.map(|m| m.brotli(enable))
+    }
+
+    /// A header sent with every request, unless the request sets it itself
+    pub fn default_header<K, V>(mut self, name: K, value: V) -> HttpBuilder
+    where
+        K: TryInto<HeaderName>,
+        K::Error: std::error::Error + Send + 'static,
+        V: TryInto<HeaderValue>,
+        V::Error: std::error::Error + Send + 'static,
+    {
+        let header = name
+            .try_into()
+            .map_err(ConveyorError::new)
+            .and_then(|name| Ok((name, value.try_into().map_err(ConveyorError::new)?)));
+        match header {
+            Ok((name, value)) => {
+                self.headers.insert(name, value);
+            }
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self
+    }
+
+    pub fn user_agent<S: AsRef<str>>(self, user_agent: S) -> HttpBuilder {
+        let user_agent = user_agent.as_ref().to_string();
+        self.map(|m| m.user_agent(user_agent))
+    }
+
+    /// Keeps cookies in `jar`, sending them with later requests.
+    /// Share the jar between clients to share a session.
+    pub fn cookie_jar(self, jar: Arc<CookieJar>) -> HttpBuilder {
+        self.map(|m| m.cookie_provider(jar))
+    }
+
+    /// Keeps cookies in a jar of its own
+    pub fn cookies(self) -> HttpBuilder {
+        self.cookie_jar(Arc::new(CookieJar::default()))
+    }
+
+    pub fn error_for_status(mut self, check: ErrorForStatus) -> HttpBuilder {
+        self.status = Some(check);
+        self
+    }
+
+    pub fn build(self) -> Result<Http> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let client = self
+            .builder
+            .default_headers(self.headers)
+            .build()
+            .map_err(ConveyorError::new)?;
+        Ok(Http {
+            client: Arc::new(client),
+            status: self.status,
+        })
+    }
+}
+
+impl Default for HttpBuilder {
+    fn default() -> HttpBuilder {
+        HttpBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::{response, serve_fn};
+    use super::super::*;
+    use super::*;
+    use std::sync::mpsc;
+
+    #[tokio::test]
+    async fn builder() {
+        // never sent to, so /slow hangs until the test is over
+        let (_release, hang) = mpsc::channel::<()>();
+        let url = serve_fn(move |request| {
+            let request = request.to_lowercase();
+            if request.starts_with("get /slow") {
+                let _ = hang.recv();
+            }
+            if request.starts_with("get /login") {
+                return response(
+                    "302 Found",
+                    &[("set-cookie", "session=1"), ("location", "/")],
+                    b"",
+                );
+            }
+            let seen = ["user-agent: crawler", "x-key: 42", "cookie: session=1"]
+                .iter()
+                .filter(|m| request.contains(*m))
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(",");
+            response("200 OK", &[], seen.as_bytes())
+        });
+
+        let jar = Arc::new(CookieJar::default());
+        let http = Http::builder()
+            .user_agent("crawler")
+            .default_header("x-key", "42")
+            .cookie_jar(jar.clone())
+            .redirects(0)
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+
+        let login = http.get(url.join("/login").unwrap()).await.unwrap();
+        assert_eq!(login.status(), StatusCode::FOUND);
+        let body = http
+            .get(url.clone())
+            .await
+            .unwrap()
+            .read_body()
+            .await
+            .unwrap();
+        assert_eq!(body, b"user-agent: crawler,x-key: 42,cookie: session=1");
+
+        // another client sharing the jar
+        let http = Http::builder().cookie_jar(jar).build().unwrap();
+        let body = http
+            .get(url.clone())
+            .await
+            .unwrap()
+            .read_body()
+            .await
+            .unwrap();
+        assert_eq!(body, b"cookie: session=1");
+
+        let http = Http::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        assert!(http.get(url.join("/slow").unwrap()).await.is_err());
+
+        assert!(Http::builder().root_certificate(b"nope").build().is_err());
+        assert!(Http::builder().default_header("x\n", "1").build().is_err());
+    }
+}
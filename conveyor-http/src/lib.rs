@@ -1,126 +1,110 @@
-//#![feature(async_await, await_macro, futures_api)]
-#![feature(async_await,async_closure)]
-
-
-use conveyor::futures::Stream as StdStream;
-use conveyor::futures_old::{Async, Future, Stream};
+use conveyor::futures::prelude::*;
 use conveyor::{ConveyorError, Result, Station};
-pub use reqwest::header::HeaderMap;
-use reqwest::r#async::{Client, Decoder};
-use reqwest::{Error, IntoUrl, StatusCode};
-use std::future::Future as StdFuture;
-use std::mem;
+pub use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
+use reqwest::Client;
+use reqwest::IntoUrl;
 use std::pin::Pin;
-use std::sync::{Arc, Mutex};
-use std::task::{Poll, Context};
-
-pub use reqwest::{
-    r#async::{Chunk, Request, Response},
-    Method, Url,
-};
-
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+pub use reqwest::{Body, Method, Proxy, Request, Response, StatusCode, Url, Version};
+
+mod builder;
+mod response;
+mod status;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod text;
+
+pub use self::builder::*;
+pub use self::response::*;
+pub use self::status::*;
+pub use self::text::*;
+pub use encoding_rs;
+
+/// Executes requests with reqwest, so the returned futures must be polled
+/// within a tokio runtime.
 #[derive(Clone, Debug)]
 pub struct Http {
     client: Arc<Client>,
+    status: Option<ErrorForStatus>,
 }
 
 impl Http {
     pub fn new() -> Http {
         Http {
             client: Arc::new(Client::new()),
+            status: None,
         }
     }
 
+    pub fn builder() -> HttpBuilder {
+        HttpBuilder::new()
+    }
+
+    /// Fails responses with a status `check` doesn't accept
+    pub fn error_for_status(mut self, check: ErrorForStatus) -> Http {
+        self.status = Some(check);
+        self
+    }
+
     pub fn get<U: IntoUrl>(&self, url: U) -> HttpFuture {
         self.execute(Request::new(Method::GET, url.into_url().unwrap()))
     }
 }
 
+impl Default for Http {
+    fn default() -> Http {
+        Http::new()
+    }
+}
+
 impl Station for Http {
     type Input = Request;
     type Output = HttpResponse;
     type Future = HttpFuture;
 
     fn execute(&self, input: Self::Input) -> Self::Future {
-        HttpFuture {
-            inner: Box::new(self.client.execute(input)),
-        }
+        let response = self.client.execute(input).map(|ret| match ret {
+            Ok(r) => Ok(HttpResponse::new(r)),
+            Err(e) => Err(ConveyorError::new(e)),
+        });
+        let inner = match self.status.clone() {
+            Some(check) => response
+                .and_then(move |response| check.check(response))
+                .boxed(),
+            None => response.boxed(),
+        };
+        HttpFuture { inner }
     }
 }
 
 pub struct HttpFuture {
-    inner: Box<Future<Item = Response, Error = Error> + Send>,
+    inner: Pin<Box<dyn Future<Output = Result<HttpResponse>> + Send>>,
 }
 
-impl StdFuture for HttpFuture {
+impl Future for HttpFuture {
     type Output = Result<HttpResponse>;
-    fn poll(self: Pin<&mut Self>, _lw: &mut Context) -> Poll<Self::Output> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
-
-        match this.inner.poll() {
-            Ok(Async::NotReady) => Poll::Pending,
-            Ok(Async::Ready(r)) => Poll::Ready(Ok(HttpResponse {
-                inner: Mutex::new(r),
-            })),
-            Err(e) => Poll::Ready(Err(ConveyorError::new(e))),
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct HttpResponse {
-    inner: Mutex<Response>,
-}
-
-impl HttpResponse {
-    pub fn status(&self) -> StatusCode {
-        self.inner.lock().unwrap().status()
-    }
-
-    pub fn headers(&self) -> HeaderMap {
-        self.inner.lock().unwrap().headers().clone()
-    }
-
-    pub fn read_body(&mut self) -> HttpBodyFuture<Vec<u8>> {
-        let body = mem::replace(self.inner.lock().unwrap().body_mut(), Decoder::empty());
-        let fut = body.concat2().map(|m| m.to_vec());
-        HttpBodyFuture(Box::new(fut))
-    }
-
-    pub fn stream(&mut self) -> HttpBodyStream<Vec<u8>> {
-        let body = mem::replace(self.inner.lock().unwrap().body_mut(), Decoder::empty());
-        HttpBodyStream(Box::new(body.map(|m| m.to_vec())))
+    fn poll(self: Pin<&mut Self>, lw: &mut Context) -> Poll<Self::Output> {
+        Pin::get_mut(self).inner.poll_unpin(lw)
     }
 }
 
-pub struct HttpBodyStream<U>(Box<Stream<Item = U, Error = Error> + Send + 'static>);
+pub struct HttpBodyStream<U>(Pin<Box<dyn Stream<Item = Result<U>> + Send + 'static>>);
 
-impl<U> StdStream for HttpBodyStream<U> {
+impl<U> Stream for HttpBodyStream<U> {
     type Item = Result<U>;
-    fn poll_next(self: Pin<&mut Self>, _waker: &mut Context) -> Poll<Option<Self::Item>> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
-        match this.0.poll() {
-            Ok(Async::NotReady) => Poll::Pending,
-            Ok(Async::Ready(r)) => match r {
-                Some(m) => Poll::Ready(Some(Ok(m))),
-                None => Poll::Ready(None),
-            },
-            Err(e) => Poll::Ready(Some(Err(ConveyorError::new(e)))),
-        }
+    fn poll_next(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Option<Self::Item>> {
+        Pin::get_mut(self).0.poll_next_unpin(waker)
     }
 }
 
-pub struct HttpBodyFuture<U>(Box<Future<Item = U, Error = Error> + Send>);
+pub struct HttpBodyFuture<U>(Pin<Box<dyn Future<Output = Result<U>> + Send>>);
 
-impl<U> StdFuture for HttpBodyFuture<U> {
+impl<U> Future for HttpBodyFuture<U> {
     type Output = Result<U>;
-    fn poll(self: Pin<&mut Self>, _lw: &mut Context) -> Poll<Self::Output> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
-        match this.0.poll() {
-            Ok(Async::NotReady) => Poll::Pending,
-            Ok(Async::Ready(r)) => Poll::Ready(Ok(r)),
-            Err(e) => Poll::Ready(Err(ConveyorError::new(e))),
-        }
+    fn poll(self: Pin<&mut Self>, lw: &mut Context) -> Poll<Self::Output> {
+        Pin::get_mut(self).0.poll_unpin(lw)
     }
 }
 
@@ -131,7 +115,7 @@ impl Station for HttpResponseReader {
     type Input = HttpResponse;
     type Output = Vec<u8>;
     type Future = HttpBodyFuture<Vec<u8>>;
-    fn execute(&self, mut input: Self::Input) -> Self::Future {
+    fn execute(&self, input: Self::Input) -> Self::Future {
         input.read_body()
     }
 }
@@ -141,11 +125,10 @@ pub struct HttpResponseStream;
 
 impl Station for HttpResponseStream {
     type Input = HttpResponse;
-    type Output =
-        Pin<Box<conveyor::futures::stream::Stream<Item = Result<Vec<u8>>> + Send + 'static>>;
-    type Future = conveyor::futures::future::Ready<Result<Self::Output>>;
-    fn execute(&self, mut input: Self::Input) -> Self::Future {
-        conveyor::futures::future::ready(Ok(Box::pin(input.stream())))
+    type Output = Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send + 'static>>;
+    type Future = future::Ready<Result<Self::Output>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        future::ready(Ok(Box::pin(input.stream())))
     }
 }
 
@@ -0,0 +1,201 @@
+use super::{
+    HeaderMap, HeaderName, HttpBodyFuture, HttpBodyStream, Response, StatusCode, Url, Version,
+};
+use conveyor::futures::prelude::*;
+use conveyor::ConveyorError;
+use reqwest::header::{CONTENT_TYPE, ETAG, LAST_MODIFIED};
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+pub struct HttpResponse {
+    inner: Response,
+}
+
+impl HttpResponse {
+    pub fn new(response: Response) -> HttpResponse {
+        HttpResponse { inner: response }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.inner.status()
+    }
+
+    /// The final url, after following redirects
+    pub fn url(&self) -> &Url {
+        self.inner.url()
+    }
+
+    pub fn version(&self) -> Version {
+        self.inner.version()
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// The length of the body, when known up front
+    pub fn content_length(&self) -> Option<u64> {
+        self.inner.content_length()
+    }
+
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.inner.remote_addr()
+    }
+
+    fn header(&self, name: HeaderName) -> Option<&str> {
+        self.headers().get(name).and_then(|m| m.to_str().ok())
+    }
+
+    /// The media type without parameters, e.g. `text/html`
+    pub fn content_type(&self) -> Option<&str> {
+        self.header(CONTENT_TYPE)
+            .and_then(|m| m.split(';').next())
+            .map(|m| m.trim())
+    }
+
+    /// The charset parameter of the content type
+    pub fn charset(&self) -> Option<&str> {
+        self.header(CONTENT_TYPE)?
+            .split(';')
+            .skip(1)
+            .filter_map(|param| {
+                let mut parts = param.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(name), Some(value)) if name.trim().eq_ignore_ascii_case("charset") => {
+                        Some(value.trim().trim_matches('"'))
+                    }
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    pub fn etag(&self) -> Option<&str> {
+        self.header(ETAG)
+    }
+
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        self.header(LAST_MODIFIED).and_then(parse_http_date)
+    }
+
+    pub fn into_inner(self) -> Response {
+        self.inner
+    }
+
+    pub fn read_body(self) -> HttpBodyFuture<Vec<u8>> {
+        HttpBodyFuture(Box::pin(async move {
+            let body = self.inner.bytes().await.map_err(ConveyorError::new)?;
+            Ok(body.to_vec())
+        }))
+    }
+
+    pub fn stream(self) -> HttpBodyStream<Vec<u8>> {
+        HttpBodyStream(Box::pin(
+            self.inner
+                .bytes_stream()
+                .map(|m| m.map(|m| m.to_vec()).map_err(ConveyorError::new)),
+        ))
+    }
+}
+
+/// Parses an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+fn parse_http_date(date: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let parts = date.split_whitespace().collect::<Vec<_>>();
+    if parts.len() != 6 || parts[5] != "GMT" {
+        return None;
+    }
+    let day = parts[1].parse::<u64>().ok()?;
+    let month = MONTHS.iter().position(|m| *m == parts[2])? as u64 + 1;
+    let year = parts[3].parse::<u64>().ok()?;
+    let time = parts[4]
+        .split(':')
+        .map(|m| m.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if time.len() != 3 || year < 1970 || time[0] > 23 || time[1] > 59 || time[2] > 60 {
+        return None;
+    }
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if day == 0 || day > month_days {
+        return None;
+    }
+
+    // days since 1970-01-01, counting years from march
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86400 + time[0] * 3600 + time[1] * 60 + time[2];
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::serve;
+    use super::super::*;
+    use super::*;
+
+    #[tokio::test]
+    async fn metadata() {
+        let url = serve(
+            "200 OK",
+            &[
+                ("content-type", "text/html; charset=\"ISO-8859-1\""),
+                ("etag", "\"abc\""),
+                ("last-modified", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            ],
+            b"hello",
+        );
+        let response = Http::new().get(url.clone()).await.unwrap();
+
+        assert_eq!(response.url(), &url);
+        assert_eq!(response.version(), Version::HTTP_11);
+        assert_eq!(response.content_length(), Some(5));
+        assert!(response.remote_addr().unwrap().ip().is_loopback());
+        assert_eq!(response.content_type(), Some("text/html"));
+        assert_eq!(response.charset(), Some("ISO-8859-1"));
+        assert_eq!(response.etag(), Some("\"abc\""));
+        assert_eq!(
+            response.last_modified(),
+            Some(UNIX_EPOCH + Duration::from_secs(784_111_777))
+        );
+        assert_eq!(response.read_body().await.unwrap(), b"hello");
+    }
+
+    #[test]
+    fn http_date() {
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 12:00:00 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_709_208_000))
+        );
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 99:99:99 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 24:00:00 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:60:00 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:61 GMT"), None);
+        assert_eq!(parse_http_date("Sat, 31 Feb 2024 12:00:00 GMT"), None);
+        assert_eq!(parse_http_date("Fri, 29 Feb 2023 12:00:00 GMT"), None);
+        assert_eq!(parse_http_date("Mon, 29 Feb 2100 12:00:00 GMT"), None);
+        assert_eq!(parse_http_date("Thu, 31 Apr 2024 12:00:00 GMT"), None);
+        assert_eq!(
+            parse_http_date("Tue, 29 Feb 2000 23:59:59 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(951_868_799))
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use super::{HeaderMap, HttpBodyFuture, HttpResponse, StatusCode, Url};
+use conveyor::futures::prelude::*;
+use conveyor::{ConveyorError, Station};
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// How much of the body a `StatusError` keeps
+const BODY_LIMIT: usize = 1024;
+
+/// A response with a status which wasn't accepted, see `ErrorForStatus`.
+/// Get it back from a `ConveyorError` with `downcast_ref`.
+#[derive(Debug, Clone)]
+pub struct StatusError {
+    status: StatusCode,
+    url: Url,
+    headers: HeaderMap,
+    body: String,
+}
+
+impl StatusError {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The start of the body, lossily decoded
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} returned {}", self.url, self.status)
+    }
+}
+
+impl Error for StatusError {}
+
+/// Fails responses with a status outside the accepted ones, 2xx by default
+#[derive(Clone, Debug)]
+pub struct ErrorForStatus {
+    accept: Vec<RangeInclusive<u16>>,
+    body_limit: usize,
+}
+
+pub fn error_for_status() -> ErrorForStatus {
+    ErrorForStatus::default()
+}
+
+impl ErrorForStatus {
+    pub fn new() -> ErrorForStatus {
+        ErrorForStatus {
+            accept: vec![200..=299],
+            body_limit: BODY_LIMIT,
+        }
+    }
+
+    pub fn accept(self, status: StatusCode) -> ErrorForStatus {
+        self.accept_range(status.as_u16()..=status.as_u16())
+    }
+
+    /// Accepts a range of statuses, e.g. `300..=399` for redirects
+    pub fn accept_range(mut self, range: RangeInclusive<u16>) -> ErrorForStatus {
+        self.accept.push(range);
+        self
+    }
+
+    /// How many bytes of the body to keep in the error, 1024 by default
+    pub fn body_limit(mut self, limit: usize) -> ErrorForStatus {
+        self.body_limit = limit;
+        self
+    }
+
+    pub fn accepts(&self, status: StatusCode) -> bool {
+        self.accept
+            .iter()
+            .any(|range| range.contains(&status.as_u16()))
+    }
+
+    pub(crate) fn check(&self, response: HttpResponse) -> HttpBodyFuture<HttpResponse> {
+        if self.accepts(response.status()) {
+            return HttpBodyFuture(Box::pin(future::ready(Ok(response))));
+        }
+
+        let limit = self.body_limit;
+        HttpBodyFuture(Box::pin(async move {
+            let mut error = StatusError {
+                status: response.status(),
+                url: response.url().clone(),
+                headers: response.headers().clone(),
+                body: String::new(),
+            };
+            let mut body = Vec::new();
+            let mut stream = response.stream();
+            while body.len() < limit {
+                match stream.next().await {
+                    Some(Ok(chunk)) => body.extend(chunk),
+                    _ => break,
+                }
+            }
+            body.truncate(limit);
+            error.body = String::from_utf8_lossy(&body).into_owned();
+            Err(ConveyorError::new(error))
+        }))
+    }
+}
+
+impl Default for ErrorForStatus {
+    fn default() -> ErrorForStatus {
+        ErrorForStatus::new()
+    }
+}
+
+impl Station for ErrorForStatus {
+    type Input = HttpResponse;
+    type Output = HttpResponse;
+    type Future = HttpBodyFuture<HttpResponse>;
+
+    fn execute(&self, input: HttpResponse) -> Self::Future {
+        self.check(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::serve;
+    use super::super::*;
+
+    #[tokio::test]
+    async fn error_for_status() {
+        let url = serve("404 Not Found", &[("x-reason", "gone")], &[b'x'; 2000]);
+        let http = Http::new().error_for_status(ErrorForStatus::new().body_limit(10));
+
+        let error = http.get(url.clone()).await.unwrap_err();
+        let error = error.downcast_ref::<StatusError>().unwrap();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+        assert_eq!(error.headers()["x-reason"], "gone");
+        assert_eq!(error.body(), "xxxxxxxxxx");
+
+        let http =
+            Http::new().error_for_status(ErrorForStatus::new().accept(StatusCode::NOT_FOUND));
+        assert!(http.get(url.clone()).await.is_ok());
+
+        // as a station
+        let response = Http::new().get(url).await.unwrap();
+        assert!(super::error_for_status().execute(response).await.is_err());
+    }
+}
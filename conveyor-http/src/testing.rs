@@ -0,0 +1,46 @@
+//! A local http server for tests, behind the `testing` feature
+
+use super::Url;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Answers every connection with `status`, `headers` and `body`, closing it afterwards
+pub fn serve(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Url {
+    let response = response(status, headers, body);
+    serve_fn(move |_| response.clone())
+}
+
+/// Answers every connection with what `handler` returns for the request head
+pub fn serve_fn<F: Fn(&str) -> Vec<u8> + Send + 'static>(handler: F) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+
+    thread::spawn(move || {
+        for mut stream in listener.incoming().filter_map(|m| m.ok()) {
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let _ = stream.write_all(&handler(&String::from_utf8_lossy(&request)));
+        }
+    });
+
+    url
+}
+
+/// A raw response closing the connection, with a content length
+pub fn response(status: &str, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+    let mut response = format!("HTTP/1.1 {}\r\nconnection: close\r\n", status);
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str(&format!("content-length: {}\r\n\r\n", body.len()));
+    let mut response = response.into_bytes();
+    response.extend_from_slice(body);
+    response
+}
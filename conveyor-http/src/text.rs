@@ -0,0 +1,118 @@
+use super::{HttpBodyFuture, HttpResponse};
+use conveyor::Station;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+/// How far into the body to look for a `<meta charset>`
+const SNIFF_LIMIT: usize = 1024;
+
+/// Picks the encoding of a body from its byte order mark, the `charset` of
+/// its content type or a `<meta charset>` near the start, in that order
+pub fn sniff_encoding(body: &[u8], charset: Option<&str>) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(body) {
+        return Some(encoding);
+    }
+    if let Some(encoding) = charset.and_then(|m| Encoding::for_label(m.as_bytes())) {
+        return Some(encoding);
+    }
+    meta_charset(&body[..body.len().min(SNIFF_LIMIT)])
+}
+
+fn meta_charset(head: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(head).to_lowercase();
+    head.split("<meta").skip(1).find_map(|tag| {
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let value = &tag[tag.find("charset=")? + "charset=".len()..];
+        let value = value.trim_start_matches(['"', '\'']);
+        let end = value
+            .find(|c: char| c == '"' || c == '\'' || c == ';' || c == '/' || c.is_whitespace())
+            .unwrap_or(value.len());
+        Encoding::for_label(&value.as_bytes()[..end])
+    })
+}
+
+/// Decodes a response body to a `String`, see `sniff_encoding`.
+/// Bytes which aren't valid in the encoding are replaced.
+#[derive(Clone, Copy, Debug)]
+pub struct DecodeText {
+    fallback: &'static Encoding,
+}
+
+pub fn decode_text() -> DecodeText {
+    DecodeText::default()
+}
+
+impl DecodeText {
+    /// The encoding used when none is found, UTF-8 by default
+    pub fn fallback(mut self, encoding: &'static Encoding) -> DecodeText {
+        self.fallback = encoding;
+        self
+    }
+
+    /// Falls back to Windows-1252, like browsers do for old html pages
+    pub fn legacy(self) -> DecodeText {
+        self.fallback(WINDOWS_1252)
+    }
+
+    pub fn decode(&self, body: &[u8], charset: Option<&str>) -> String {
+        let encoding = sniff_encoding(body, charset).unwrap_or(self.fallback);
+        let (text, _, _) = encoding.decode(body);
+        text.into_owned()
+    }
+}
+
+impl Default for DecodeText {
+    fn default() -> DecodeText {
+        DecodeText { fallback: UTF_8 }
+    }
+}
+
+impl Station for DecodeText {
+    type Input = HttpResponse;
+    type Output = String;
+    type Future = HttpBodyFuture<String>;
+
+    fn execute(&self, input: HttpResponse) -> Self::Future {
+        let decode = *self;
+        let charset = input.charset().map(|m| m.to_string());
+        let body = input.read_body();
+        HttpBodyFuture(Box::pin(async move {
+            let body = body.await?;
+            Ok(decode.decode(&body, charset.as_deref()))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::serve;
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn sniff() {
+        let latin1 = b"caf\xe9";
+        assert_eq!(decode_text().decode(latin1, Some("ISO-8859-1")), "café");
+        assert_eq!(decode_text().decode(latin1, None), "caf\u{fffd}");
+        assert_eq!(decode_text().legacy().decode(latin1, None), "café");
+
+        let html = b"<html><head><META http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\"></head>caf\xe9";
+        assert!(decode_text().decode(html, None).ends_with("café"));
+        let html = b"<meta charset='iso-8859-1'/>caf\xe9";
+        assert!(decode_text().decode(html, None).ends_with("café"));
+
+        // the bom wins over the header
+        let bom = b"\xef\xbb\xbfcaf\xc3\xa9";
+        assert_eq!(decode_text().decode(bom, Some("ISO-8859-1")), "café");
+    }
+
+    #[tokio::test]
+    async fn response() {
+        let url = serve(
+            "200 OK",
+            &[("content-type", "text/plain; charset=iso-8859-1")],
+            b"caf\xe9",
+        );
+        let response = Http::new().get(url).await.unwrap();
+        assert_eq!(decode_text().execute(response).await.unwrap(), "café");
+    }
+}
@@ -1,14 +1,11 @@
 use super::package::{Package, PackageContent};
-use conveyor::futures_old::{
-    sync::mpsc::{channel, Receiver as FutureReceiver, Sender as FutureSender},
-    Async, Future as OldFuture, Sink, Stream,
-};
+use conveyor::futures::channel::mpsc::{channel, Receiver as FutureReceiver};
+use conveyor::futures::prelude::*;
 use conveyor::*;
+use pin_project::pin_project;
 use std::io::Read;
 use std::pin::Pin;
-use std::task::{Poll, Waker};
-use std::thread;
-use vfs::boxed::*;
+use std::task::{Context, Poll};
 use vfs::prelude::*;
 
 pub enum FSRequest {
@@ -16,6 +13,7 @@ pub enum FSRequest {
     Glob(String),
 }
 
+#[pin_project]
 pub struct FS<V: VFS>
 where
     <V as VFS>::Path: ReadPath,
@@ -29,31 +27,47 @@ where
     <V as VFS>::Path: ReadPath,
 {
     pub fn glob<S: AsRef<str>>(fs: V, glob: S) -> FS<V> {
-        let (sx, rx) = channel::<Result<V::Path>>(0);
+        FS::glob_with(&ThreadSpawner, fs, glob).expect("could not spawn walker")
+    }
+
+    pub fn glob_with<Sp: Spawner + ?Sized, S: AsRef<str>>(
+        spawner: &Sp,
+        fs: V,
+        glob: S,
+    ) -> Result<FS<V>> {
+        let (mut sx, rx) = channel::<Result<V::Path>>(0);
         let glob = glob.as_ref().to_string();
-        tokio::spawn_async(
-            async move {
-                for i in fs.path("").glob_walk(glob) {
-                    tokio::await!(sx.clone().send(Ok(i))).expect("could not send");
+        spawner.spawn(async move {
+            for i in fs.path("").glob_walk(glob) {
+                if sx.send(Ok(i)).await.is_err() {
+                    break;
                 }
-            },
-        );
+            }
+        })?;
 
-        FS { rx }
+        Ok(FS { rx })
     }
 
     pub fn path<S: AsRef<str>>(fs: V, path: S) -> FS<V> {
-        let (sx, rx) = channel::<Result<V::Path>>(0);
+        FS::path_with(&ThreadSpawner, fs, path).expect("could not spawn walker")
+    }
+
+    pub fn path_with<Sp: Spawner + ?Sized, S: AsRef<str>>(
+        spawner: &Sp,
+        fs: V,
+        path: S,
+    ) -> Result<FS<V>> {
+        let (mut sx, rx) = channel::<Result<V::Path>>(0);
         let path = path.as_ref().to_string();
-        tokio::spawn_async(
-            async move {
-                for i in fs.path(&path).walk_dir() {
-                    tokio::await!(sx.clone().send(Ok(i))).expect("could not send");
+        spawner.spawn(async move {
+            for i in fs.path(&path).walk_dir() {
+                if sx.send(Ok(i)).await.is_err() {
+                    break;
                 }
-            },
-        );
+            }
+        })?;
 
-        FS { rx }
+        Ok(FS { rx })
     }
 }
 
@@ -63,32 +77,37 @@ where
 {
     type Item = Result<V::Path>;
 
-    fn poll_next(self: Pin<&mut Self>, _waker: &Waker) -> Poll<Option<Self::Item>> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
-
-        match this.rx.poll() {
-            Ok(m) => match m {
-                Async::NotReady => Poll::Pending,
-                Async::Ready(ret) => Poll::Ready(ret),
-            },
-            Err(_) => panic!("should not happen"),
-        }
+    fn poll_next(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Option<Self::Item>> {
+        self.project().rx.poll_next_unpin(waker)
     }
 }
 
+#[pin_project]
 pub struct FileProducer<Str: futures::Stream<Item = Result<V>>, V: ReadPath> {
+    #[pin]
     stream: Str,
     station: WorkStation<V, Package>,
 }
 
 impl<Str: futures::Stream<Item = Result<V>>, V: ReadPath + 'static> FileProducer<Str, V> {
     pub fn new(stream: Str, nwork: usize, read: bool) -> FileProducer<Str, V> {
-        FileProducer {
+        FileProducer::with_spawner(&ThreadSpawner, stream, nwork, read)
+            .expect("could not spawn worker")
+    }
+
+    pub fn with_spawner<Sp: Spawner + ?Sized>(
+        spawner: &Sp,
+        stream: Str,
+        nwork: usize,
+        read: bool,
+    ) -> Result<FileProducer<Str, V>> {
+        Ok(FileProducer {
             stream,
-            station: WorkStation::new(
+            station: WorkStation::with_spawner(
+                spawner,
                 nwork,
                 move |path: V, _ctx: &mut ()| match path.open() {
-                    Err(e) => return Err(ConveyorError::new(e)),
+                    Err(e) => Err(ConveyorError::new(e)),
                     Ok(mut file) => {
                         let content = if read {
                             let mut buf = Vec::new();
@@ -103,8 +122,8 @@ impl<Str: futures::Stream<Item = Result<V>>, V: ReadPath + 'static> FileProducer
                     }
                 },
                 || (),
-            ),
-        }
+            )?,
+        })
     }
 }
 
@@ -120,10 +139,10 @@ impl<Str: futures::Stream<Item = Result<V>>, V: ReadPath + 'static> futures::Str
         futures::future::Ready<Result<Package>>,
         Result<Package>,
     >;
-    fn poll_next(self: Pin<&mut Self>, waker: &Waker) -> Poll<Option<Self::Item>> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
+    fn poll_next(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.project();
 
-        match unsafe { Pin::new_unchecked(&mut this.stream) }.poll_next(waker) {
+        match this.stream.poll_next(waker) {
             Poll::Pending => Poll::Pending,
             Poll::Ready(s) => match s {
                 None => Poll::Ready(None),
@@ -143,44 +162,57 @@ impl<Str: futures::Stream<Item = Result<V>>, V: ReadPath + 'static> futures::Str
 #[cfg(test)]
 mod tests {
 
-    use super::super::traits::ChainStreamer;
     use super::*;
-    use conveyor::futures::prelude::*;
-    use conveyor::station_fn;
-    use conveyor::{ConcurrentStream, WorkStation};
-    use tokio;
+    use conveyor::{ConcurrentStream, TestExecutor};
     use vfs::physical;
 
+    #[tokio::test]
+    async fn test_vfs() {
+        let fs = FS::glob(physical::PhysicalFS::new("../").unwrap(), "**/*.rs");
+
+        let fs = FileProducer::new(fs, 1, true);
+
+        let fs = ConcurrentStream::new(fs, 4);
+
+        // let fs = fs.then(|p: Result<Package>| async move {
+        //     let mut p = match p {
+        //         Ok(p) => p,
+        //         Err(e) => return Err(e),
+        //     };
+        //     let content = p.read_content().await?;
+        //     Ok(p.set_value(content))
+        // });
+
+        fs.then(|m| async move {
+            let name = match m {
+                Ok(mut m) => {
+                    let _content = m.read_content().await;
+                    m.name().to_string()
+                }
+                Err(_) => return,
+            };
+            println!("{}", name);
+        })
+        .collect::<Vec<_>>()
+        .await;
+    }
+
     #[test]
-    fn test_vfs() {
-        tokio::run_async(
-            async {
-                let fs = FS::glob(physical::PhysicalFS::new("../").unwrap(), "**/*.rs");
-
-                let fs = FileProducer::new(fs, 1, true);
-
-                let fs = ConcurrentStream::new(fs, 4);
-
-                // let fs = fs.then(async move |p: Result<Package>| {
-                //     let mut p = match p {
-                //         Ok(p) => p,
-                //         Err(e) => return Err(e),
-                //     };
-                //     let content = await!(p.read_content())?;
-                //     Ok(p.set_value(content))
-                // });
-
-                await!(fs
-                    .then(async move |m| {
-                        let (content, name) = match m {
-                            Ok(mut m) => (await!(m.read_content()), m.name().to_string()),
-                            Err(e) => return (),
-                        };
-                        //let content = await!(m..read_content());
-                        println!("{}", name);
-                    })
-                    .collect::<Vec<_>>());
-            },
+    fn test_vfs_spawner() {
+        let executor = TestExecutor::new();
+        let fs = FS::glob_with(
+            &executor,
+            physical::PhysicalFS::new("./").unwrap(),
+            "src/*.rs",
+        )
+        .unwrap();
+        let fs = FileProducer::with_spawner(&executor, fs, 2, true).unwrap();
+
+        let names = executor.block_on(
+            fs.then(|m| m)
+                .map(|m| m.unwrap().name().to_string())
+                .collect::<Vec<_>>(),
         );
+        assert!(names.iter().any(|m| m.ends_with("fs.rs")));
     }
 }
@@ -0,0 +1,385 @@
+use super::super::package::{Package, PackageContent};
+use conveyor::futures::channel::mpsc::channel;
+use conveyor::futures::executor::block_on;
+use conveyor::futures::prelude::*;
+use conveyor::futures::stream::BoxStream;
+use conveyor::Spawner;
+use conveyor_http::{Body, HeaderValue};
+use serde_json::Value;
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+type Chunks = BoxStream<'static, io::Result<Vec<u8>>>;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The body of a request.
+/// `Content` and parts made from a `Package` can't be serialized.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpBody {
+    #[default]
+    Empty,
+    Text(String),
+    Bytes(Vec<u8>),
+    Json(Value),
+    /// Url encoded, in order
+    Form(Vec<(String, String)>),
+    Multipart(Vec<Part>),
+    /// Streamed as it's read, without buffering streams or readers
+    #[serde(skip)]
+    Content(PackageContent),
+}
+
+/// A field of a multipart body
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Part {
+    pub name: String,
+    #[serde(flatten)]
+    pub content: PartContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartContent {
+    Text(String),
+    Bytes(Vec<u8>),
+    /// Read from disk when the request is sent
+    File(PathBuf),
+    #[serde(skip)]
+    Package(Package),
+}
+
+impl Part {
+    pub fn text<N: Into<String>, S: Into<String>>(name: N, text: S) -> Part {
+        Part::new(name, PartContent::Text(text.into()))
+    }
+
+    pub fn file<N: Into<String>, P: Into<PathBuf>>(name: N, path: P) -> Part {
+        let path = path.into();
+        let filename = path.file_name().map(|m| m.to_string_lossy().into_owned());
+        let mut part = Part::new(name, PartContent::File(path));
+        part.filename = filename;
+        part
+    }
+
+    /// A file part named after the package, streaming its content
+    pub fn package<N: Into<String>>(name: N, package: Package) -> Part {
+        let filename = package.name().to_string();
+        let mut part = Part::new(name, PartContent::Package(package));
+        part.filename = Some(filename);
+        part
+    }
+
+    fn new<N: Into<String>>(name: N, content: PartContent) -> Part {
+        Part {
+            name: name.into(),
+            content,
+            filename: None,
+            content_type: None,
+        }
+    }
+
+    pub fn filename<S: Into<String>>(mut self, filename: S) -> Part {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    pub fn content_type<S: Into<String>>(mut self, content_type: S) -> Part {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    fn into_chunks(self, boundary: &str, spawner: &Arc<dyn Spawner>) -> Chunks {
+        let mut head = format!(
+            "--{}\r\ncontent-disposition: form-data; name=\"{}\"",
+            boundary,
+            escape(&self.name)
+        );
+        if let Some(filename) = &self.filename {
+            head.push_str(&format!("; filename=\"{}\"", escape(filename)));
+        }
+        if let Some(content_type) = &self.content_type {
+            // a header value can't hold line breaks which would end the header
+            if let Err(e) = HeaderValue::from_str(content_type) {
+                let error = io::Error::new(io::ErrorKind::InvalidInput, e);
+                return stream::once(future::ready(Err(error))).boxed();
+            }
+            head.push_str(&format!("\r\ncontent-type: {}", content_type));
+        }
+        head.push_str("\r\n\r\n");
+
+        let content = match self.content {
+            PartContent::Text(text) => once(text.into_bytes()),
+            PartContent::Bytes(bytes) => once(bytes),
+            PartContent::File(path) => read_chunks(spawner.clone(), move || File::open(path)),
+            PartContent::Package(package) => content_chunks(package.into_content(), spawner),
+        };
+        once(head.into_bytes())
+            .chain(content)
+            .chain(once(b"\r\n".to_vec()))
+            .boxed()
+    }
+}
+
+fn once(chunk: Vec<u8>) -> Chunks {
+    stream::once(future::ready(Ok(chunk))).boxed()
+}
+
+/// Percent encodes quotes and line breaks in a name or filename, like
+/// browsers do for html forms
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn content_chunks(content: PackageContent, spawner: &Arc<dyn Spawner>) -> Chunks {
+    match content {
+        PackageContent::Bytes(bytes) => once(bytes),
+        PackageContent::Stream(stream) => {
+            stream.map_err(|e| io::Error::other(e.to_string())).boxed()
+        }
+        PackageContent::Reader(reader) => read_chunks(spawner.clone(), move || Ok(reader)),
+        PackageContent::Empty => stream::empty().boxed(),
+    }
+}
+
+/// Reads what `open` returns in chunks as blocking work on `spawner`, once
+/// the stream is polled, so reading doesn't hold up the executor
+fn read_chunks<R, F>(spawner: Arc<dyn Spawner>, open: F) -> Chunks
+where
+    R: Read + Send + 'static,
+    F: FnOnce() -> io::Result<R> + Send + 'static,
+{
+    stream::once(future::lazy(move |_| {
+        let (mut sx, rx) = channel::<io::Result<Vec<u8>>>(0);
+        let ret = spawner.spawn_blocking_boxed(Box::new(move || {
+            let mut reader = match open() {
+                Ok(reader) => reader,
+                Err(e) => {
+                    let _ = block_on(sx.send(Err(e)));
+                    return;
+                }
+            };
+            loop {
+                let mut chunk = vec![0; CHUNK_SIZE];
+                let ret = match reader.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        chunk.truncate(n);
+                        Ok(chunk)
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => Err(e),
+                };
+                let failed = ret.is_err();
+                if block_on(sx.send(ret)).is_err() || failed {
+                    break;
+                }
+            }
+        }));
+        match ret {
+            Ok(()) => rx.boxed(),
+            Err(e) => stream::once(future::ready(Err(io::Error::other(e.to_string())))).boxed(),
+        }
+    }))
+    .flatten()
+    .boxed()
+}
+
+impl HttpBody {
+    pub fn is_empty(&self) -> bool {
+        matches!(self, HttpBody::Empty)
+    }
+
+    /// The content type to send when the request doesn't set one
+    pub(super) fn content_type(&self, boundary: &str) -> Option<String> {
+        match self {
+            HttpBody::Text(_) => Some("text/plain; charset=utf-8".to_string()),
+            HttpBody::Json(_) => Some("application/json".to_string()),
+            HttpBody::Form(_) => Some("application/x-www-form-urlencoded".to_string()),
+            HttpBody::Multipart(_) => Some(format!("multipart/form-data; boundary={}", boundary)),
+            _ => None,
+        }
+    }
+
+    pub(super) fn into_chunks(self, boundary: &str, spawner: &Arc<dyn Spawner>) -> Chunks {
+        match self {
+            HttpBody::Empty => stream::empty().boxed(),
+            HttpBody::Text(text) => once(text.into_bytes()),
+            HttpBody::Bytes(bytes) => once(bytes),
+            HttpBody::Json(value) => once(serde_json::to_vec(&value).unwrap()),
+            HttpBody::Form(fields) => once(
+                url::form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(fields)
+                    .finish()
+                    .into_bytes(),
+            ),
+            HttpBody::Multipart(parts) => {
+                let boundary = boundary.to_string();
+                let end = once(format!("--{}--\r\n", boundary).into_bytes());
+                let spawner = spawner.clone();
+                stream::iter(parts)
+                    .map(move |part| part.into_chunks(&boundary, &spawner))
+                    .flatten()
+                    .chain(end)
+                    .boxed()
+            }
+            HttpBody::Content(content) => content_chunks(content, spawner),
+        }
+    }
+
+    pub(super) fn into_body(self, boundary: &str, spawner: &Arc<dyn Spawner>) -> Option<Body> {
+        match self {
+            HttpBody::Empty => None,
+            HttpBody::Text(text) => Some(Body::from(text)),
+            HttpBody::Bytes(bytes) => Some(Body::from(bytes)),
+            body => Some(Body::wrap_stream(body.into_chunks(boundary, spawner))),
+        }
+    }
+}
+
+pub(super) fn boundary() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    format!("conveyor-{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conveyor::futures::executor::block_on;
+    use conveyor::ThreadSpawner;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn chunks(body: HttpBody) -> Chunks {
+        body.into_chunks("b", &(Arc::new(ThreadSpawner) as Arc<dyn Spawner>))
+    }
+
+    fn encode(body: HttpBody) -> String {
+        let chunks = block_on(chunks(body).try_concat()).unwrap();
+        String::from_utf8(chunks).unwrap()
+    }
+
+    #[test]
+    fn encoding() {
+        let body = HttpBody::Form(vec![("q".to_string(), "a b&c".to_string())]);
+        assert_eq!(encode(body), "q=a+b%26c");
+
+        let body = HttpBody::Json(serde_json::json!({ "a": 1 }));
+        assert_eq!(encode(body), "{\"a\":1}");
+
+        let stream: std::pin::Pin<Box<dyn Stream<Item = conveyor::Result<Vec<u8>>> + Send>> =
+            Box::pin(stream::iter(vec![Ok(b"da".to_vec()), Ok(b"ta".to_vec())]));
+        let package = Package::new("data.txt", stream);
+        let body = HttpBody::Multipart(vec![
+            Part::text("title", "hi"),
+            Part::package("file", package).content_type("text/plain"),
+        ]);
+        assert_eq!(
+            encode(body),
+            "--b\r\ncontent-disposition: form-data; name=\"title\"\r\n\r\nhi\r\n\
+             --b\r\ncontent-disposition: form-data; name=\"file\"; filename=\"data.txt\"\r\n\
+             content-type: text/plain\r\n\r\ndata\r\n--b--\r\n"
+        );
+    }
+
+    #[test]
+    fn read_chunks() {
+        let data = (0..CHUNK_SIZE * 2 + 10)
+            .map(|m| (m % 251) as u8)
+            .collect::<Vec<_>>();
+        let path = std::env::temp_dir().join(format!("conveyor-body-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let body = HttpBody::Multipart(vec![Part::file("file", &path)]);
+        let read = block_on(chunks(body).try_collect::<Vec<_>>()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(read.iter().all(|m| m.len() <= CHUNK_SIZE));
+        assert_eq!(read[1..read.len() - 2].concat(), data);
+
+        let reader = PackageContent::Reader(Box::new(io::Cursor::new(data.clone())));
+        let content = block_on(chunks(HttpBody::Content(reader)).try_concat());
+        assert_eq!(content.unwrap(), data);
+
+        let missing = Part::file("file", "/conveyor/missing");
+        let body = chunks(HttpBody::Multipart(vec![missing])).try_concat();
+        assert!(block_on(body).is_err());
+    }
+
+    /// Counts the blocking tasks it runs
+    #[derive(Default)]
+    struct Counting(AtomicUsize);
+
+    impl Spawner for Counting {
+        fn spawn_boxed(&self, future: future::BoxFuture<'static, ()>) -> conveyor::Result<()> {
+            ThreadSpawner.spawn_boxed(future)
+        }
+
+        fn spawn_blocking_boxed(&self, task: Box<dyn FnOnce() + Send>) -> conveyor::Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            ThreadSpawner.spawn_blocking_boxed(task)
+        }
+    }
+
+    #[test]
+    fn spawner() {
+        let spawner = Arc::new(Counting::default());
+        let reader = PackageContent::Reader(Box::new(io::Cursor::new(b"data".to_vec())));
+        let body = HttpBody::Multipart(vec![
+            Part::text("title", "hi"),
+            Part::package("file", Package::new("data.txt", reader)),
+        ]);
+        let chunks = body.into_chunks("b", &(spawner.clone() as Arc<dyn Spawner>));
+        assert_eq!(spawner.0.load(Ordering::SeqCst), 0);
+        block_on(chunks.try_concat()).unwrap();
+        assert_eq!(spawner.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn part_headers() {
+        let part = Part::text("a\"\r\nx-injected: 1", "").filename("b\nc.txt");
+        let body = encode(HttpBody::Multipart(vec![part]));
+        assert!(body.starts_with(
+            "--b\r\ncontent-disposition: form-data; \
+             name=\"a%22%0D%0Ax-injected: 1\"; filename=\"b%0Ac.txt\"\r\n\r\n"
+        ));
+
+        let part = Part::text("a", "").content_type("text/plain\r\nx-injected: 1");
+        let body = chunks(HttpBody::Multipart(vec![part])).try_concat();
+        assert!(block_on(body).is_err());
+    }
+
+    #[test]
+    fn serde() {
+        let body = HttpBody::Multipart(vec![
+            Part::text("title", "hi"),
+            Part::file("file", "/tmp/data.txt"),
+        ]);
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "multipart": [
+                { "name": "title", "text": "hi" },
+                { "name": "file", "file": "/tmp/data.txt", "filename": "data.txt" },
+            ]})
+        );
+        let body = serde_json::from_value::<HttpBody>(json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&body).unwrap()["multipart"][1]["filename"],
+            "data.txt"
+        );
+
+        let content = HttpBody::Content(PackageContent::Empty);
+        assert!(serde_json::to_value(&content).is_err());
+    }
+}
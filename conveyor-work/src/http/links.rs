@@ -0,0 +1,449 @@
+use super::super::package::Package;
+use super::HttpOptions;
+use conveyor::work::{Work, WorkOutput};
+use conveyor::{ConveyorError, Result, Station};
+use std::collections::HashSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkKind {
+    /// `<a href>`
+    Anchor,
+    /// `<link href>`
+    Link,
+    /// `<img src>`
+    Image,
+}
+
+/// Which hosts links may point to, compared to the page they're found on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkScope {
+    Any,
+    /// The same host only
+    Host,
+    /// The same host or one of its subdomains
+    Domain,
+}
+
+/// Turns an html `Package` into GET requests for the links in it.
+/// Links are resolved against the package name, which `HttpProducer` sets to
+/// the url of the page, or a `<base href>` in the page. The scope is always
+/// checked against the url of the page.
+#[derive(Debug, Clone)]
+pub struct LinkExtractor {
+    inner: Arc<Options>,
+}
+
+#[derive(Debug, Clone)]
+struct Options {
+    kinds: Vec<LinkKind>,
+    scope: LinkScope,
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+pub fn extract_links() -> LinkExtractor {
+    LinkExtractor::new()
+}
+
+impl LinkExtractor {
+    pub fn new() -> LinkExtractor {
+        LinkExtractor {
+            inner: Arc::new(Options {
+                kinds: vec![LinkKind::Anchor, LinkKind::Link, LinkKind::Image],
+                scope: LinkScope::Host,
+                include: Vec::new(),
+                exclude: Vec::new(),
+            }),
+        }
+    }
+
+    fn options(&mut self) -> &mut Options {
+        Arc::make_mut(&mut self.inner)
+    }
+
+    /// The kinds of links to follow, all of them by default
+    pub fn kinds(mut self, kinds: &[LinkKind]) -> LinkExtractor {
+        self.options().kinds = kinds.to_vec();
+        self
+    }
+
+    /// `LinkScope::Host` by default
+    pub fn scope(mut self, scope: LinkScope) -> LinkExtractor {
+        self.options().scope = scope;
+        self
+    }
+
+    /// Only follows urls matching one of the included patterns.
+    /// `*` matches any run of characters, e.g. `https://*.example.com/docs/*`
+    pub fn include<S: Into<String>>(mut self, pattern: S) -> LinkExtractor {
+        self.options().include.push(pattern.into());
+        self
+    }
+
+    /// Never follows urls matching the pattern, see `include`
+    pub fn exclude<S: Into<String>>(mut self, pattern: S) -> LinkExtractor {
+        self.options().exclude.push(pattern.into());
+        self
+    }
+
+    /// The urls of the links in `html`, the page at `page`, in order and
+    /// without duplicates
+    pub fn extract(&self, page: &Url, html: &str) -> Vec<Url> {
+        let options = &self.inner;
+        let mut base = page.clone();
+        let mut seen = HashSet::new();
+        let mut links = Vec::new();
+
+        for tag in Tags::new(html) {
+            let (kind, attr) = match tag.name.as_str() {
+                "base" => {
+                    if let Some(url) = tag.attr("href").and_then(|m| base.join(m).ok()) {
+                        base = url;
+                    }
+                    continue;
+                }
+                "a" => (LinkKind::Anchor, "href"),
+                "link" => (LinkKind::Link, "href"),
+                "img" => (LinkKind::Image, "src"),
+                _ => continue,
+            };
+            if !options.kinds.contains(&kind) {
+                continue;
+            }
+            let mut url = match tag.attr(attr).and_then(|m| base.join(m.trim()).ok()) {
+                Some(url) => url,
+                None => continue,
+            };
+            url.set_fragment(None);
+            if options.follows(page, &url) && seen.insert(url.to_string()) {
+                links.push(url);
+            }
+        }
+
+        links
+    }
+}
+
+impl Default for LinkExtractor {
+    fn default() -> LinkExtractor {
+        LinkExtractor::new()
+    }
+}
+
+impl Options {
+    fn follows(&self, page: &Url, url: &Url) -> bool {
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return false;
+        }
+        let in_scope = match (self.scope, page.host_str(), url.host_str()) {
+            (LinkScope::Any, _, _) => true,
+            (LinkScope::Host, Some(page), Some(host)) => page.eq_ignore_ascii_case(host),
+            (LinkScope::Domain, Some(page), Some(host)) => {
+                let (page, host) = (page.to_lowercase(), host.to_lowercase());
+                host == page || host.ends_with(&format!(".{}", page))
+            }
+            _ => false,
+        };
+        let url = url.as_str();
+        in_scope
+            && (self.include.is_empty() || self.include.iter().any(|m| wildcard(m, url)))
+            && !self.exclude.iter().any(|m| wildcard(m, url))
+    }
+}
+
+impl Station for LinkExtractor {
+    type Input = Package;
+    type Output = Vec<HttpOptions>;
+    type Future = Pin<Box<dyn Future<Output = Result<Vec<HttpOptions>>> + Send>>;
+
+    fn execute(&self, mut input: Package) -> Self::Future {
+        let extractor = self.clone();
+        Box::pin(async move {
+            let page = Url::parse(input.name()).map_err(ConveyorError::new)?;
+            let body = input.read_content().await?;
+            let html = String::from_utf8_lossy(&body);
+            Ok(extractor
+                .extract(&page, &html)
+                .into_iter()
+                .map(|url| HttpOptions::new(super::Method::GET, url))
+                .collect())
+        })
+    }
+}
+
+/// Turns the requests into work for the station registered as `station`,
+/// keyed by url so a page is only crawled once
+pub fn into_work<S: AsRef<str>>(
+    requests: Vec<HttpOptions>,
+    station: S,
+) -> Vec<WorkOutput<HttpOptions>> {
+    requests
+        .into_iter()
+        .map(|options| {
+            let key = options.url.to_string();
+            WorkOutput::Work(Work::named(options, station.as_ref()).with_key(key))
+        })
+        .collect()
+}
+
+/// Matches `text` against `pattern`, where `*` matches any run of characters
+pub(super) fn wildcard(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !text.starts_with(first) {
+        return false;
+    }
+    let mut rest = &text[first.len()..];
+    let parts = parts.collect::<Vec<_>>();
+    let (last, middle) = match parts.split_last() {
+        Some(split) => split,
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+struct Tag {
+    name: String,
+    attrs: Vec<(String, String)>,
+}
+
+impl Tag {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Replaces the entities which commonly show up in urls
+pub(super) fn decode_entities(value: &str) -> String {
+    value
+        .replace("&#38;", "&")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// The start tags of an html document, skipping comments and the contents of
+/// `<script>` and `<style>`
+struct Tags<'a> {
+    html: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tags<'a> {
+    fn new(html: &'a str) -> Tags<'a> {
+        Tags { html, pos: 0 }
+    }
+
+    /// Moves past the next `end`, ignoring ascii case
+    fn skip_past(&mut self, end: &str) {
+        let (rest, end) = (&self.html.as_bytes()[self.pos..], end.as_bytes());
+        self.pos = match rest
+            .windows(end.len())
+            .position(|m| m.eq_ignore_ascii_case(end))
+        {
+            Some(idx) => self.pos + idx + end.len(),
+            None => self.html.len(),
+        };
+    }
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = Tag;
+
+    fn next(&mut self) -> Option<Tag> {
+        loop {
+            let start = self.pos + self.html[self.pos..].find('<')?;
+            self.pos = start + 1;
+            let rest = &self.html[self.pos..];
+            if rest.starts_with("!--") {
+                self.skip_past("-->");
+                continue;
+            }
+            if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                continue;
+            }
+
+            let (tag, len) = parse_tag(rest);
+            self.pos += len;
+            if tag.name == "script" || tag.name == "style" {
+                let end = format!("</{}", tag.name);
+                self.skip_past(&end);
+            }
+            return Some(tag);
+        }
+    }
+}
+
+/// Parses a start tag, without the leading `<`, returning it and its length
+fn parse_tag(input: &str) -> (Tag, usize) {
+    let bytes = input.as_bytes();
+    let mut pos = 0;
+    let until = |pos: &mut usize, stop: &dyn Fn(u8) -> bool| {
+        let start = *pos;
+        while *pos < bytes.len() && !stop(bytes[*pos]) {
+            *pos += 1;
+        }
+        &input[start..*pos]
+    };
+    let end_of_name = |c: u8| c.is_ascii_whitespace() || c == b'>' || c == b'/' || c == b'=';
+
+    let name = until(&mut pos, &end_of_name).to_ascii_lowercase();
+    let mut attrs = Vec::new();
+    loop {
+        until(&mut pos, &|c| !c.is_ascii_whitespace() && c != b'/');
+        if pos >= bytes.len() || bytes[pos] == b'>' {
+            break;
+        }
+        let key = until(&mut pos, &end_of_name).to_ascii_lowercase();
+        until(&mut pos, &|c| !c.is_ascii_whitespace());
+        let value = if bytes.get(pos) == Some(&b'=') {
+            pos += 1;
+            until(&mut pos, &|c| !c.is_ascii_whitespace());
+            match bytes.get(pos) {
+                Some(&quote) if quote == b'"' || quote == b'\'' => {
+                    pos += 1;
+                    let value = until(&mut pos, &|c| c == quote);
+                    pos = (pos + 1).min(bytes.len());
+                    value
+                }
+                _ => until(&mut pos, &|c| c.is_ascii_whitespace() || c == b'>'),
+            }
+        } else {
+            ""
+        };
+        if key.is_empty() {
+            pos += 1;
+            continue;
+        }
+        attrs.push((key, decode_entities(value)));
+    }
+
+    (Tag { name, attrs }, (pos + 1).min(bytes.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conveyor::futures::executor::block_on;
+
+    const PAGE: &str = r#"<html><head>
+        <link rel="stylesheet" href="/style.css">
+        <script>var a = "<a href='/script'>";</script>
+        </head><body>
+        <!-- <a href="/comment"> -->
+        <A HREF=about.html>About</A>
+        <a href="/docs/intro#top">Intro</a>
+        <a href='/docs/intro'>Again</a>
+        <a href="/search?q=1&amp;page=2">Search</a>
+        <img alt="logo" src="https://cdn.example.com/logo.png" />
+        <a href="https://other.org/">Other</a>
+        <a href="mailto:me@example.com">Mail</a>
+        <a href="/private/admin">Admin</a>
+        </body></html>"#;
+
+    fn urls(extractor: LinkExtractor) -> Vec<String> {
+        let base = Url::parse("https://example.com/dir/page.html").unwrap();
+        extractor
+            .extract(&base, PAGE)
+            .into_iter()
+            .map(|m| m.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn extract() {
+        assert_eq!(
+            urls(extract_links()),
+            vec![
+                "https://example.com/style.css",
+                "https://example.com/dir/about.html",
+                "https://example.com/docs/intro",
+                "https://example.com/search?q=1&page=2",
+                "https://example.com/private/admin",
+            ]
+        );
+        assert_eq!(
+            urls(
+                extract_links()
+                    .scope(LinkScope::Domain)
+                    .kinds(&[LinkKind::Image])
+            ),
+            vec!["https://cdn.example.com/logo.png"]
+        );
+        assert_eq!(
+            urls(
+                extract_links()
+                    .scope(LinkScope::Any)
+                    .include("https://*.org/*")
+                    .include("*/docs/*")
+            ),
+            vec!["https://example.com/docs/intro", "https://other.org/"]
+        );
+        assert_eq!(
+            urls(
+                extract_links()
+                    .kinds(&[LinkKind::Anchor])
+                    .exclude("*/private/*")
+            )
+            .len(),
+            3
+        );
+
+        let base = Url::parse("https://example.com/").unwrap();
+        let html = "<base href='https://example.com/v2/'><a href=page>";
+        assert_eq!(
+            extract_links().extract(&base, html)[0].as_str(),
+            "https://example.com/v2/page"
+        );
+
+        // the base only resolves links, the scope stays the page's host
+        let html = "<base href='https://cdn.example.net/'><a href=page><a href='/home'>";
+        assert!(extract_links().extract(&base, html).is_empty());
+        let html = "<base href='https://cdn.example.net/'><a href='https://example.com/a'>";
+        assert_eq!(
+            extract_links().extract(&base, html)[0].as_str(),
+            "https://example.com/a"
+        );
+
+        let html = "<SCRIPT>'<a href=/no>'</ScRiPt><a href=/yes>";
+        assert_eq!(
+            extract_links().extract(&base, html)[0].as_str(),
+            "https://example.com/yes"
+        );
+    }
+
+    #[test]
+    fn station() {
+        let package = Package::new("https://example.com/", PAGE.as_bytes().to_vec());
+        let requests = block_on(extract_links().execute(package)).unwrap();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[0].url.as_str(), "https://example.com/style.css");
+
+        let work = into_work(requests, "fetch");
+        match &work[1] {
+            WorkOutput::Work(work) => {
+                assert_eq!(work.key(), Some("https://example.com/about.html"))
+            }
+            WorkOutput::Result(_) => panic!("expected work"),
+        }
+
+        let package = Package::new("not a url", Vec::new());
+        assert!(block_on(extract_links().execute(package)).is_err());
+    }
+}
@@ -0,0 +1,647 @@
+use super::package::{Package, PackageContent};
+use super::utils::{BoxWrap, BoxedStation};
+use conveyor::futures::channel::mpsc;
+use conveyor::futures::prelude::*;
+use conveyor::{Chain, ConveyorError, ConveyorFuture, Result, Spawner, Station, ThreadSpawner};
+use conveyor_http::header::{AUTHORIZATION, CONTENT_TYPE};
+use conveyor_http::{HeaderMap, HeaderValue, HttpFuture, HttpResponse};
+use std::convert::TryFrom;
+use std::fmt;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use url::Url;
+
+mod body;
+mod links;
+mod robots;
+mod sitemap;
+
+pub use self::body::*;
+pub use self::links::*;
+pub use self::robots::*;
+pub use self::sitemap::*;
+
+macro_rules! method_impl {
+    ($name: ident, $method: ident) => {
+        pub fn $name<S: AsRef<str>>(url: S) -> Result<HttpOptions> {
+            HttpOptions::request(Method::$method, url)
+        }
+    };
+}
+
+/// Serialized as the method name, e.g. `"GET"`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(into = "String", try_from = "String")]
+pub enum Method {
+    GET,
+    POST,
+    PUT,
+    DELETE,
+    HEAD,
+    PATCH,
+    OPTIONS,
+    /// Any other method, e.g. `PURGE`
+    Other(conveyor_http::Method),
+}
+
+impl Method {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::GET => "GET",
+            Method::POST => "POST",
+            Method::PUT => "PUT",
+            Method::DELETE => "DELETE",
+            Method::HEAD => "HEAD",
+            Method::PATCH => "PATCH",
+            Method::OPTIONS => "OPTIONS",
+            Method::Other(method) => method.as_str(),
+        }
+    }
+}
+
+impl From<conveyor_http::Method> for Method {
+    fn from(method: conveyor_http::Method) -> Method {
+        match method.as_str() {
+            "GET" => Method::GET,
+            "POST" => Method::POST,
+            "PUT" => Method::PUT,
+            "DELETE" => Method::DELETE,
+            "HEAD" => Method::HEAD,
+            "PATCH" => Method::PATCH,
+            "OPTIONS" => Method::OPTIONS,
+            _ => Method::Other(method),
+        }
+    }
+}
+
+impl From<Method> for conveyor_http::Method {
+    fn from(method: Method) -> conveyor_http::Method {
+        match method {
+            Method::GET => conveyor_http::Method::GET,
+            Method::POST => conveyor_http::Method::POST,
+            Method::PUT => conveyor_http::Method::PUT,
+            Method::DELETE => conveyor_http::Method::DELETE,
+            Method::HEAD => conveyor_http::Method::HEAD,
+            Method::PATCH => conveyor_http::Method::PATCH,
+            Method::OPTIONS => conveyor_http::Method::OPTIONS,
+            Method::Other(method) => method,
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = ConveyorError;
+
+    fn from_str(s: &str) -> Result<Method> {
+        conveyor_http::Method::from_bytes(s.as_bytes())
+            .map(Method::from)
+            .map_err(ConveyorError::new)
+    }
+}
+
+impl TryFrom<String> for Method {
+    type Error = ConveyorError;
+
+    fn try_from(s: String) -> Result<Method> {
+        s.parse()
+    }
+}
+
+impl From<Method> for String {
+    fn from(method: Method) -> String {
+        method.as_str().to_string()
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpAuth {
+    Basic {
+        username: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password: Option<String>,
+    },
+    Bearer(String),
+}
+
+impl HttpAuth {
+    fn header(&self) -> Result<HeaderValue> {
+        let value = match self {
+            HttpAuth::Basic { username, password } => {
+                use base64::Engine;
+                let credentials = format!("{}:{}", username, password.as_deref().unwrap_or(""));
+                format!(
+                    "Basic {}",
+                    base64::engine::general_purpose::STANDARD.encode(credentials)
+                )
+            }
+            HttpAuth::Bearer(token) => format!("Bearer {}", token),
+        };
+        let mut value = HeaderValue::from_str(&value).map_err(ConveyorError::new)?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HttpOptions {
+    pub method: Method,
+    pub url: Url,
+    #[serde(default, with = "crate::serde_headers")]
+    pub headers: HeaderMap,
+    #[serde(default, skip_serializing_if = "HttpBody::is_empty")]
+    pub body: HttpBody,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<HttpAuth>,
+    /// Overrides the timeout of the client for this request, serialized
+    /// in milliseconds
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::serde_millis"
+    )]
+    pub timeout: Option<Duration>,
+    /// Streams the body into the package instead of reading it first.
+    /// Off unless asked for, as most stations read the whole body anyway.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+    #[serde(skip)]
+    pub station: Option<BoxedStation>,
+}
+
+impl HttpOptions {
+    pub fn new(method: Method, url: Url) -> HttpOptions {
+        HttpOptions {
+            method,
+            url,
+            headers: HeaderMap::new(),
+            body: HttpBody::Empty,
+            auth: None,
+            timeout: None,
+            stream: false,
+            station: None,
+        }
+    }
+
+    pub fn request<S: AsRef<str>>(method: Method, url: S) -> Result<HttpOptions> {
+        let url = Url::parse(url.as_ref()).map_err(ConveyorError::new)?;
+        Ok(HttpOptions::new(method, url))
+    }
+
+    /// Appends `pairs` to the query of the url
+    pub fn query<K: AsRef<str>, V: AsRef<str>, I: IntoIterator<Item = (K, V)>>(
+        mut self,
+        pairs: I,
+    ) -> Self {
+        self.url.query_pairs_mut().extend_pairs(pairs);
+        self
+    }
+
+    pub fn basic_auth<U: Into<String>>(mut self, username: U, password: Option<String>) -> Self {
+        self.auth = Some(HttpAuth::Basic {
+            username: username.into(),
+            password,
+        });
+        self
+    }
+
+    pub fn bearer_auth<T: Into<String>>(mut self, token: T) -> Self {
+        self.auth = Some(HttpAuth::Bearer(token.into()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Hands the body to the package as a `PackageContent::Stream` as it
+    /// arrives, so large downloads aren't buffered in memory. Off by default.
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn body(mut self, body: HttpBody) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn json<T: serde::Serialize>(self, value: &T) -> Result<Self> {
+        let value = serde_json::to_value(value).map_err(ConveyorError::new)?;
+        Ok(self.body(HttpBody::Json(value)))
+    }
+
+    pub fn form<K: Into<String>, V: Into<String>, I: IntoIterator<Item = (K, V)>>(
+        self,
+        fields: I,
+    ) -> Self {
+        let fields = fields
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        self.body(HttpBody::Form(fields))
+    }
+
+    pub fn multipart(self, parts: Vec<Part>) -> Self {
+        self.body(HttpBody::Multipart(parts))
+    }
+
+    method_impl!(get, GET);
+    method_impl!(post, POST);
+    method_impl!(put, PUT);
+    method_impl!(delete, DELETE);
+    method_impl!(head, HEAD);
+    method_impl!(patch, PATCH);
+    method_impl!(options, OPTIONS);
+
+    /// Fails when the credentials can't be sent in a header
+    pub fn to_request(self) -> Result<conveyor_http::Request> {
+        self.to_request_with(Arc::new(ThreadSpawner))
+    }
+
+    /// Like `to_request`, reading file and reader parts of the body as
+    /// blocking work on `spawner`
+    pub fn to_request_with(self, spawner: Arc<dyn Spawner>) -> Result<conveyor_http::Request> {
+        let mut ret = conveyor_http::Request::new(self.method.into(), self.url);
+        *ret.timeout_mut() = self.timeout;
+        let boundary = body::boundary();
+        let mut headers = self.headers;
+        if let Some(auth) = &self.auth {
+            if !headers.contains_key(AUTHORIZATION) {
+                headers.insert(AUTHORIZATION, auth.header()?);
+            }
+        }
+        if let Some(content_type) = self.body.content_type(&boundary) {
+            if !headers.contains_key(CONTENT_TYPE) {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_str(&content_type).unwrap());
+            }
+        }
+        *ret.headers_mut() = headers;
+        *ret.body_mut() = self.body.into_body(&boundary, &spawner);
+        Ok(ret)
+    }
+
+    pub fn station<S: Station<Input = Package, Output = Package> + Send + Sync + 'static>(
+        mut self,
+        station: S,
+    ) -> Self {
+        self.station = Some(conveyor::into_box(station));
+        self
+    }
+}
+
+pub struct HttpProducer {
+    client: conveyor_http::Http,
+    requests: Pin<Box<dyn Stream<Item = HttpOptions> + Send>>,
+    spawner: Arc<dyn Spawner>,
+}
+
+impl HttpProducer {
+    pub fn new(requests: Vec<HttpOptions>) -> HttpProducer {
+        HttpProducer::from_stream(stream::iter(requests))
+    }
+
+    /// Sends requests as `requests` yields them, ending when it does
+    pub fn from_stream<S: Stream<Item = HttpOptions> + Send + 'static>(
+        requests: S,
+    ) -> HttpProducer {
+        HttpProducer {
+            client: conveyor_http::Http::new(),
+            requests: Box::pin(requests),
+            spawner: Arc::new(ThreadSpawner),
+        }
+    }
+
+    /// A producer which sends the requests pushed to the handle. It ends
+    /// once every handle is dropped and the pushed requests are sent.
+    pub fn channel() -> (HttpProducer, HttpProducerHandle) {
+        let (sender, receiver) = mpsc::unbounded();
+        (
+            HttpProducer::from_stream(receiver),
+            HttpProducerHandle { sender },
+        )
+    }
+
+    pub fn with_client(mut self, client: conveyor_http::Http) -> HttpProducer {
+        self.client = client;
+        self
+    }
+
+    /// Reads file and reader parts of request bodies on `spawner`,
+    /// e.g. a tokio `Handle` to use its blocking pool. Defaults to a thread per part
+    pub fn with_spawner<S: Spawner + 'static>(mut self, spawner: S) -> HttpProducer {
+        self.spawner = Arc::new(spawner);
+        self
+    }
+}
+
+/// Pushes requests to a producer made with `HttpProducer::channel`
+#[derive(Clone)]
+pub struct HttpProducerHandle {
+    sender: mpsc::UnboundedSender<HttpOptions>,
+}
+
+/// The producer of a `HttpProducerHandle` was dropped
+#[derive(Debug)]
+pub struct ProducerClosed {
+    pub url: Url,
+}
+
+impl fmt::Display for ProducerClosed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "producer closed before {} was pushed", self.url)
+    }
+}
+
+impl std::error::Error for ProducerClosed {}
+
+impl HttpProducerHandle {
+    pub fn push(&self, request: HttpOptions) -> Result<()> {
+        self.sender.unbounded_send(request).map_err(|e| {
+            ConveyorError::new(ProducerClosed {
+                url: e.into_inner().url,
+            })
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+/// Reads up to `limit` bytes of the body, dropping the rest
+async fn read_at_most(response: HttpResponse, limit: usize) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut stream = response.stream();
+    while body.len() < limit {
+        match stream.next().await {
+            Some(chunk) => {
+                let chunk = chunk?;
+                let len = chunk.len().min(limit - body.len());
+                body.extend_from_slice(&chunk[..len]);
+            }
+            None => break,
+        }
+    }
+    Ok(body)
+}
+
+/// Turns a response into a package, reading the body first or streaming it
+#[derive(Clone, Debug)]
+pub struct ResponsePackage {
+    name: String,
+    stream: bool,
+}
+
+impl ResponsePackage {
+    pub fn new<S: AsRef<str>>(name: S, stream: bool) -> ResponsePackage {
+        ResponsePackage {
+            name: name.as_ref().to_string(),
+            stream,
+        }
+    }
+}
+
+impl Station for ResponsePackage {
+    type Input = HttpResponse;
+    type Output = Package;
+    type Future = Pin<Box<dyn Future<Output = Result<Package>> + Send>>;
+
+    fn execute(&self, input: HttpResponse) -> Self::Future {
+        let name = self.name.clone();
+        if self.stream {
+            let content = PackageContent::Stream(Box::pin(input.stream()));
+            return future::ready(Ok(Package::new(name, content))).boxed();
+        }
+        input
+            .read_body()
+            .map_ok(move |body| Package::new(name, body))
+            .boxed()
+    }
+}
+
+type RequestFuture =
+    ConveyorFuture<ConveyorFuture<HttpFuture, ResponsePackage, HttpResponse>, BoxWrap, Package>;
+
+impl Stream for HttpProducer {
+    /// Fails right away for requests which can't be built
+    type Item = future::Either<RequestFuture, future::Ready<Result<Package>>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = Pin::get_mut(self);
+
+        let mut next = match this.requests.poll_next_unpin(cx) {
+            Poll::Ready(Some(next)) => next,
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let chain = this
+            .client
+            .clone()
+            .pipe(ResponsePackage::new(next.url.as_str(), next.stream));
+
+        let chain = if next.station.is_some() {
+            let m = next.station.take().unwrap();
+            chain.pipe(boxwrap!(m))
+        } else {
+            chain.pipe(boxwrap!(conveyor::into_box(conveyor::station_fn(
+                |p: Package| future::ready(Ok(p)),
+            ))))
+        };
+
+        let ret = match next.to_request_with(this.spawner.clone()) {
+            Ok(request) => future::Either::Left(chain.execute(request)),
+            Err(e) => future::Either::Right(future::ready(Err(e))),
+        };
+        Poll::Ready(Some(ret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::traits::ChainStreamer;
+    use super::*;
+    use conveyor::station_fn;
+    use conveyor::{ConcurrentStream, WorkStation};
+    use conveyor_http::testing::{response, serve_fn};
+    use std::error::Error;
+    use std::result::Result;
+    use tokio;
+
+    #[test]
+    fn request_body() {
+        let request = HttpOptions::post("http://localhost/")
+            .unwrap()
+            .form(vec![("a", "1")])
+            .to_request()
+            .unwrap();
+        assert_eq!(
+            request.headers()["content-type"],
+            "application/x-www-form-urlencoded"
+        );
+
+        let mut options = HttpOptions::post("http://localhost/")
+            .unwrap()
+            .body(HttpBody::Text("hi".to_string()));
+        options
+            .headers
+            .insert("content-type", "text/html".parse().unwrap());
+        let json = serde_json::to_string(&options).unwrap();
+        let request = serde_json::from_str::<HttpOptions>(&json)
+            .unwrap()
+            .to_request()
+            .unwrap();
+        assert_eq!(request.headers()["content-type"], "text/html");
+        assert_eq!(request.body().unwrap().as_bytes(), Some(&b"hi"[..]));
+    }
+
+    #[test]
+    fn request_options() {
+        let options = HttpOptions::request("PURGE".parse().unwrap(), "http://localhost/?a=1")
+            .unwrap()
+            .query(vec![("q", "a b")])
+            .basic_auth("user", Some("pass".to_string()))
+            .timeout(Duration::from_secs(5));
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(json["method"], "PURGE");
+        assert_eq!(json["url"], "http://localhost/?a=1&q=a+b");
+        assert_eq!(json["timeout"], 5000);
+
+        let request = serde_json::from_value::<HttpOptions>(json)
+            .unwrap()
+            .to_request()
+            .unwrap();
+        assert_eq!(request.method().as_str(), "PURGE");
+        assert_eq!(request.timeout(), Some(&Duration::from_secs(5)));
+        assert_eq!(request.headers()["authorization"], "Basic dXNlcjpwYXNz");
+
+        let options = HttpOptions::patch("http://localhost/")
+            .unwrap()
+            .bearer_auth("token");
+        let json = serde_json::to_string(&options).unwrap();
+        let options = serde_json::from_str::<HttpOptions>(&json).unwrap();
+        assert_eq!(options.method, Method::PATCH);
+        assert_eq!(options.auth, Some(HttpAuth::Bearer("token".to_string())));
+        assert!("GET POST".parse::<Method>().is_err());
+
+        let options = HttpOptions::get("http://localhost/")
+            .unwrap()
+            .bearer_auth("token\r\nx-injected: 1");
+        assert!(options.to_request().is_err());
+    }
+
+    #[tokio::test]
+    async fn http_producer_channel() {
+        let url = serve_fn(|request| {
+            let path = request.split_whitespace().nth(1).unwrap_or("");
+            response("200 OK", &[], path.as_bytes())
+        });
+        let (mut producer, handle) = HttpProducer::channel();
+        handle
+            .push(HttpOptions::get(url.join("/a").unwrap().as_str()).unwrap())
+            .unwrap();
+
+        let mut package = producer.next().await.unwrap().await.unwrap();
+        assert_eq!(package.read_content().await.unwrap(), b"/a");
+
+        // requests discovered along the way
+        let more = handle.clone();
+        more.push(HttpOptions::get(url.join("/b").unwrap().as_str()).unwrap())
+            .unwrap();
+        drop(more);
+        drop(handle);
+
+        let ret = producer.then(|m| m).collect::<Vec<_>>().await;
+        assert_eq!(ret.len(), 1);
+        assert_eq!(
+            ret[0].as_ref().unwrap().name(),
+            url.join("/b").unwrap().as_str()
+        );
+
+        let (producer, handle) = HttpProducer::channel();
+        drop(producer);
+        assert!(handle.is_closed());
+        let err = handle.push(HttpOptions::get(url.as_str()).unwrap());
+        assert!(err.unwrap_err().downcast_ref::<ProducerClosed>().is_some());
+    }
+
+    #[tokio::test]
+    async fn http_producer_stream() {
+        let body = vec![b'x'; 256 * 1024];
+        let url = serve_fn(move |_| response("200 OK", &[], &body));
+
+        let options = HttpOptions::get(url.as_str()).unwrap().stream(true);
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(json["stream"], true);
+        let options = serde_json::from_value::<HttpOptions>(json).unwrap();
+
+        let mut producer = HttpProducer::new(vec![options]);
+        let package = producer.next().await.unwrap().await.unwrap();
+        let chunks = match package.into_content() {
+            PackageContent::Stream(stream) => stream.try_collect::<Vec<_>>().await.unwrap(),
+            content => panic!("expected a stream, got {:?}", content),
+        };
+        assert_eq!(chunks.iter().map(|m| m.len()).sum::<usize>(), 256 * 1024);
+
+        let options = HttpOptions::get(url.as_str()).unwrap();
+        assert!(serde_json::to_value(&options)
+            .unwrap()
+            .get("stream")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn http_producer() -> Result<(), Box<dyn Error>> {
+        let producer = HttpProducer::new(vec![
+            HttpOptions::get("https://skuffesalg.nu/")
+                .unwrap()
+                .station(station_fn(|p: Package| async move {
+                    println!("skuffe salg nu");
+                    Ok(p)
+                })),
+            HttpOptions::get("https://www.telmore.dk/").unwrap(),
+            HttpOptions::get("https://www.valdemarsro.dk/blinis-med-stenbiderrogn/").unwrap(),
+            HttpOptions::get("https://distrowatch.org").unwrap(),
+            HttpOptions::get("https://bolighed.dk").unwrap(),
+        ]);
+
+        let stream = producer
+            .pipe(station_fn(|mut m: Package| async move {
+                println!("done {}", m.name());
+                let name = m.name().to_string();
+                let value = m.read_content().await?;
+                println!("done2 {}", m.name());
+                Ok(Package::new(name, value))
+            }))
+            .pipe(WorkStation::new(
+                4,
+                |p: Package, _ctx: &mut String| {
+                    println!("thread {:?} {}", std::thread::current().id(), p.name());
+                    Ok(p)
+                },
+                || String::from("Hello"),
+            ));
+
+        let stream = ConcurrentStream::new(stream, 4);
+
+        let ret = stream.collect::<Vec<_>>().await;
+
+        for r in ret {
+            println!("ret {}", r.unwrap().name());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,444 @@
+use super::links::wildcard;
+use super::{read_at_most, HttpOptions};
+use conveyor::futures::future::{BoxFuture, Shared};
+use conveyor::futures::prelude::*;
+use conveyor::producer::{Clock, SystemClock};
+use conveyor::{Result, Station};
+use conveyor_http::header::USER_AGENT;
+use conveyor_http::{HeaderValue, Http, Method, Request, StatusError};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+/// How much of a robots.txt is read, like Google does
+const SIZE_LIMIT: usize = 500 * 1024;
+
+/// Longer crawl-delays are cut down to this
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+/// The rules of a robots.txt which apply to one user agent
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+    sitemaps: Vec<String>,
+}
+
+impl Robots {
+    /// Picks the groups naming the product token of `user_agent`,
+    /// e.g. `mybot` for `MyBot/1.0`, or the `*` groups when none do
+    pub fn parse(text: &str, user_agent: &str) -> Robots {
+        let token = user_agent
+            .split(|c: char| c == '/' || c.is_whitespace())
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+
+        let mut groups: Vec<Group> = Vec::new();
+        let mut sitemaps = Vec::new();
+        let mut in_rules = true;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let (key, value) = match line.find(':') {
+                Some(idx) => (line[..idx].trim().to_lowercase(), line[idx + 1..].trim()),
+                None => continue,
+            };
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        groups.push(Group::default());
+                        in_rules = false;
+                    }
+                    groups.last_mut().unwrap().agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    if let (Some(group), false) = (groups.last_mut(), value.is_empty()) {
+                        group.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    let delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|m| m.is_finite() && *m >= 0.0)
+                        .map(|m| Duration::from_secs_f64(m.min(MAX_CRAWL_DELAY.as_secs_f64())));
+                    if let (Some(group), Some(delay)) = (groups.last_mut(), delay) {
+                        group.crawl_delay = Some(delay);
+                    }
+                }
+                "sitemap" if !value.is_empty() => sitemaps.push(value.to_string()),
+                _ => {}
+            }
+        }
+
+        let named = groups.iter().any(|m| m.agents.contains(&token));
+        let mut robots = Robots {
+            sitemaps,
+            ..Robots::default()
+        };
+        for group in groups.into_iter().filter(|group| {
+            group
+                .agents
+                .iter()
+                .any(|m| if named { *m == token } else { m == "*" })
+        }) {
+            robots.rules.extend(group.rules);
+            robots.crawl_delay = robots.crawl_delay.or(group.crawl_delay);
+        }
+        robots
+    }
+
+    pub fn allow_all() -> Robots {
+        Robots::default()
+    }
+
+    pub fn disallow_all() -> Robots {
+        Robots {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            ..Robots::default()
+        }
+    }
+
+    /// Whether `path`, including the query, may be crawled.
+    /// The longest matching rule wins and allow wins ties.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|rule| matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .map(|rule| rule.allow)
+            .unwrap_or(true)
+    }
+
+    /// At most an hour
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    /// The urls of the `Sitemap` lines, which apply to every user agent
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+}
+
+/// Rules match a prefix of the path, unless they end with `$`
+fn matches(pattern: &str, path: &str) -> bool {
+    if let Some(pattern) = pattern.strip_suffix('$') {
+        wildcard(pattern, path)
+    } else {
+        wildcard(&format!("{}*", pattern), path)
+    }
+}
+
+/// The rules, and whether they stand in for a robots.txt which couldn't be fetched
+type Fetched = (Arc<Robots>, bool);
+
+#[derive(Default)]
+struct Cache {
+    /// When the robots.txt of an origin was requested, and the request
+    entries: HashMap<String, (SystemTime, Shared<BoxFuture<'static, Fetched>>)>,
+    /// When the next request to an origin with a crawl-delay may be sent
+    next: HashMap<String, SystemTime>,
+    /// The number of entries after expired ones were last dropped
+    swept: usize,
+}
+
+/// Drops requests the robots.txt of their host disallows, and delays requests
+/// to hosts with a crawl-delay so they're at least that far apart.
+///
+/// The robots.txt is fetched once per host and kept for a day. A missing
+/// robots.txt (4xx) allows everything, while server and network errors
+/// disallow everything until it's fetched again a minute later.
+#[derive(Clone)]
+pub struct RobotsFilter {
+    client: Http,
+    user_agent: String,
+    clock: Arc<dyn Clock>,
+    ttl: Duration,
+    retry: Duration,
+    cache: Arc<Mutex<Cache>>,
+}
+
+pub fn robots<S: Into<String>>(user_agent: S) -> RobotsFilter {
+    RobotsFilter::new(user_agent)
+}
+
+impl RobotsFilter {
+    /// `user_agent` picks the rules and is sent when fetching robots.txt
+    pub fn new<S: Into<String>>(user_agent: S) -> RobotsFilter {
+        RobotsFilter {
+            client: Http::new(),
+            user_agent: user_agent.into(),
+            clock: Arc::new(SystemClock),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            retry: Duration::from_secs(60),
+            cache: Arc::new(Mutex::new(Cache::default())),
+        }
+    }
+
+    pub fn with_client(mut self, client: Http) -> RobotsFilter {
+        self.client = client;
+        self
+    }
+
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> RobotsFilter {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// How long a fetched robots.txt is used
+    pub fn ttl(mut self, ttl: Duration) -> RobotsFilter {
+        self.ttl = ttl;
+        self
+    }
+
+    /// How long to wait before fetching a robots.txt again after a server
+    /// or network error
+    pub fn retry(mut self, retry: Duration) -> RobotsFilter {
+        self.retry = retry;
+        self
+    }
+
+    /// Whether an entry requested at `at` is still used at `now`. Requests
+    /// still running are.
+    fn fresh(
+        &self,
+        at: SystemTime,
+        rules: &Shared<BoxFuture<'static, Fetched>>,
+        now: SystemTime,
+    ) -> bool {
+        match rules.peek() {
+            Some((_, failed)) => {
+                let ttl = if *failed { self.retry } else { self.ttl };
+                at.checked_add(ttl).is_none_or(|m| m > now)
+            }
+            None => true,
+        }
+    }
+
+    /// The rules for the host of `url`, fetching them when they aren't cached
+    pub fn rules(&self, url: &Url) -> impl Future<Output = Arc<Robots>> {
+        let origin = url.origin().ascii_serialization();
+        let now = self.clock.now();
+        let mut cache = self.cache.lock().unwrap();
+        let rules = match cache.entries.get(&origin) {
+            Some((at, rules)) if self.fresh(*at, rules, now) => rules.clone(),
+            _ => {
+                // drop expired entries whenever their number doubled
+                if cache.entries.len() >= cache.swept.max(16) * 2 {
+                    cache
+                        .entries
+                        .retain(|_, (at, rules)| self.fresh(*at, rules, now));
+                    cache.next.retain(|_, at| *at > now);
+                    cache.swept = cache.entries.len();
+                }
+                let rules = self.fetch(&origin).boxed().shared();
+                cache.entries.insert(origin, (now, rules.clone()));
+                rules
+            }
+        };
+        rules.map(|(rules, _)| rules)
+    }
+
+    fn fetch(&self, origin: &str) -> impl Future<Output = Fetched> {
+        let client = self.client.clone();
+        let user_agent = self.user_agent.clone();
+        let url = Url::parse(origin).and_then(|m| m.join("/robots.txt"));
+        async move {
+            let mut request = match url {
+                Ok(url) => Request::new(Method::GET, url),
+                Err(_) => return (Arc::new(Robots::disallow_all()), true),
+            };
+            if let Ok(value) = HeaderValue::from_str(&user_agent) {
+                request.headers_mut().insert(USER_AGENT, value);
+            }
+            let (robots, failed) = match client.execute(request).await {
+                Ok(response) if response.status().is_success() => {
+                    match read_at_most(response, SIZE_LIMIT).await {
+                        Ok(body) => (
+                            Robots::parse(&String::from_utf8_lossy(&body), &user_agent),
+                            false,
+                        ),
+                        Err(_) => (Robots::disallow_all(), true),
+                    }
+                }
+                Ok(response) if response.status().is_client_error() => (Robots::allow_all(), false),
+                Err(e)
+                    if e.downcast_ref::<StatusError>()
+                        .map(|m| m.status().is_client_error())
+                        .unwrap_or(false) =>
+                {
+                    (Robots::allow_all(), false)
+                }
+                _ => (Robots::disallow_all(), true),
+            };
+            (Arc::new(robots), failed)
+        }
+    }
+
+    /// When a request to `origin` may be sent, reserving the slot
+    fn slot(&self, origin: String, delay: Duration) -> SystemTime {
+        let now = self.clock.now();
+        let next = &mut self.cache.lock().unwrap().next;
+        let at = next
+            .get(&origin)
+            .cloned()
+            .filter(|m| *m > now)
+            .unwrap_or(now);
+        next.insert(origin, at.checked_add(delay).unwrap_or(at));
+        at
+    }
+}
+
+impl Station for RobotsFilter {
+    type Input = HttpOptions;
+    type Output = Option<HttpOptions>;
+    type Future = Pin<Box<dyn Future<Output = Result<Option<HttpOptions>>> + Send>>;
+
+    fn execute(&self, input: HttpOptions) -> Self::Future {
+        if input.url.scheme() != "http" && input.url.scheme() != "https" {
+            return future::ready(Ok(Some(input))).boxed();
+        }
+        let filter = self.clone();
+        let rules = self.rules(&input.url);
+        async move {
+            let rules = rules.await;
+            let path = match input.url.query() {
+                Some(query) => format!("{}?{}", input.url.path(), query),
+                None => input.url.path().to_string(),
+            };
+            if !rules.is_allowed(&path) {
+                return Ok(None);
+            }
+            if let Some(delay) = rules.crawl_delay() {
+                let at = filter.slot(input.url.origin().ascii_serialization(), delay);
+                filter.clock.sleep_until(at).await;
+            }
+            Ok(Some(input))
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conveyor::producer::ManualClock;
+    use conveyor_http::testing::{response, serve_fn};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::UNIX_EPOCH;
+
+    const ROBOTS: &str = "
+        User-agent: *
+        Disallow: /
+
+        User-agent: other
+        User-agent: MyBot
+        Allow: /private/open
+        Disallow: /private # not for crawlers
+        Disallow: /*.pdf$
+        Crawl-delay: 10
+
+        Sitemap: https://example.com/sitemap.xml
+    ";
+
+    #[test]
+    fn parse() {
+        let robots = Robots::parse(ROBOTS, "MyBot/1.0 (+https://example.com)");
+        assert!(robots.is_allowed("/"));
+        assert!(robots.is_allowed("/robots.txt"));
+        assert!(!robots.is_allowed("/private/page"));
+        assert!(robots.is_allowed("/private/open/page"));
+        assert!(!robots.is_allowed("/files/a.pdf"));
+        assert!(robots.is_allowed("/files/a.pdf?page=1"));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_secs(10)));
+        assert_eq!(robots.sitemaps(), ["https://example.com/sitemap.xml"]);
+
+        let robots = Robots::parse(ROBOTS, "somebot");
+        assert!(!robots.is_allowed("/"));
+        assert_eq!(robots.crawl_delay(), None);
+
+        let robots = Robots::parse("User-agent: *\nDisallow: /\nAllow: /a\nDisallow: /a", "bot");
+        assert!(robots.is_allowed("/a"));
+        assert!(!robots.is_allowed("/b"));
+
+        let delay = |value: &str| {
+            Robots::parse(&format!("User-agent: *\nCrawl-delay: {}", value), "bot").crawl_delay()
+        };
+        assert_eq!(delay("0.5"), Some(Duration::from_millis(500)));
+        assert_eq!(delay("inf"), None);
+        assert_eq!(delay("NaN"), None);
+        assert_eq!(delay("-1"), None);
+        assert_eq!(delay("1e20"), Some(MAX_CRAWL_DELAY));
+    }
+
+    #[tokio::test]
+    async fn filter() {
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let counter = fetched.clone();
+        let url = serve_fn(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if request.to_lowercase().contains("user-agent: mybot") {
+                response("200 OK", &[], ROBOTS.as_bytes())
+            } else {
+                response("500 Internal Server Error", &[], b"")
+            }
+        });
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let filter = robots("MyBot").with_clock(clock.clone());
+        let request = |path: &str| HttpOptions::get(url.join(path).unwrap().as_str()).unwrap();
+
+        let allowed = filter.execute(request("/page")).await.unwrap();
+        assert_eq!(allowed.unwrap().url.path(), "/page");
+        assert!(filter.execute(request("/private")).await.unwrap().is_none());
+        assert_eq!(fetched.load(Ordering::SeqCst), 1);
+
+        // the crawl-delay holds the next request back
+        let mut delayed = filter.execute(request("/other"));
+        assert!(conveyor::futures::poll!(&mut delayed).is_pending());
+        clock.advance(Duration::from_secs(10));
+        assert!(delayed.await.unwrap().is_some());
+
+        // errors disallow everything until the robots.txt is fetched again
+        let filter = robots("somebot").with_clock(clock.clone());
+        assert!(filter.execute(request("/")).await.unwrap().is_none());
+        assert!(filter.execute(request("/")).await.unwrap().is_none());
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+        clock.advance(Duration::from_secs(60));
+        assert!(filter.execute(request("/")).await.unwrap().is_none());
+        assert_eq!(fetched.load(Ordering::SeqCst), 3);
+
+        // while a fetched robots.txt is kept
+        let filter = robots("MyBot").with_clock(clock.clone());
+        assert!(filter.execute(request("/page")).await.unwrap().is_some());
+        clock.advance(Duration::from_secs(60 * 60));
+        assert!(filter.execute(request("/page")).await.unwrap().is_some());
+        assert_eq!(fetched.load(Ordering::SeqCst), 4);
+    }
+}
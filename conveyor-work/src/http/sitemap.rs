@@ -0,0 +1,257 @@
+use super::links::decode_entities;
+use super::{read_at_most, HttpOptions, Method, Robots};
+use conveyor::futures::future::BoxFuture;
+use conveyor::futures::prelude::*;
+use conveyor::{ConveyorError, Result, Station};
+use conveyor_http::{error_for_status, Http};
+use flate2::read::GzDecoder;
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use url::Url;
+
+/// How much of a sitemap is read, before and after decompressing, which is
+/// the most the sitemaps protocol allows
+const SIZE_LIMIT: usize = 50 * 1024 * 1024;
+
+/// The locations listed in a sitemap
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sitemap {
+    /// Pages, from a `<urlset>` or a text sitemap
+    pub urls: Vec<String>,
+    /// Other sitemaps, from a `<sitemapindex>`
+    pub sitemaps: Vec<String>,
+}
+
+impl Sitemap {
+    /// Parses an xml sitemap, a sitemap index or a text sitemap with a url per line
+    pub fn parse(text: &str) -> Sitemap {
+        let text = text.trim_start_matches('\u{feff}').trim();
+        if !text.starts_with('<') {
+            return Sitemap {
+                urls: text
+                    .lines()
+                    .map(|m| m.trim())
+                    .filter(|m| !m.is_empty())
+                    .map(|m| m.to_string())
+                    .collect(),
+                sitemaps: Vec::new(),
+            };
+        }
+
+        let locs = text
+            .split("<loc")
+            .skip(1)
+            .filter_map(|m| {
+                let value = &m[m.find('>')? + 1..];
+                let value = &value[..value.find("</loc")?];
+                let value = value.trim();
+                let value = value
+                    .strip_prefix("<![CDATA[")
+                    .and_then(|m| m.strip_suffix("]]>"))
+                    .unwrap_or(value);
+                Some(decode_entities(value.trim()))
+            })
+            .collect();
+        if text.contains("<sitemapindex") {
+            Sitemap {
+                urls: Vec::new(),
+                sitemaps: locs,
+            }
+        } else {
+            Sitemap {
+                urls: locs,
+                sitemaps: Vec::new(),
+            }
+        }
+    }
+}
+
+/// Produces GET requests for the pages listed in sitemaps, following
+/// sitemap indexes. Every sitemap is fetched once. One which can't be fetched
+/// is produced as an error, and the rest are still fetched. Gzipped
+/// sitemaps are decompressed.
+pub struct SitemapProducer {
+    client: Http,
+    sitemaps: VecDeque<Url>,
+    seen: HashSet<Url>,
+    urls: VecDeque<Url>,
+    fetching: Option<BoxFuture<'static, Result<(Url, Sitemap)>>>,
+}
+
+impl SitemapProducer {
+    pub fn new(sitemaps: Vec<Url>) -> SitemapProducer {
+        SitemapProducer {
+            client: Http::new(),
+            seen: sitemaps.iter().cloned().collect(),
+            sitemaps: VecDeque::from(sitemaps),
+            urls: VecDeque::new(),
+            fetching: None,
+        }
+    }
+
+    /// The sitemaps listed in a robots.txt, skipping invalid urls
+    pub fn from_robots(robots: &Robots) -> SitemapProducer {
+        SitemapProducer::new(
+            robots
+                .sitemaps()
+                .iter()
+                .filter_map(|m| Url::parse(m).ok())
+                .collect(),
+        )
+    }
+
+    pub fn with_client(mut self, client: Http) -> SitemapProducer {
+        self.client = client;
+        self
+    }
+
+    fn fetch(&self, url: Url) -> BoxFuture<'static, Result<(Url, Sitemap)>> {
+        let response = self.client.get(url.clone());
+        async move {
+            let response = error_for_status().execute(response.await?).await?;
+            let body = gunzip(read_at_most(response, SIZE_LIMIT).await?)?;
+            let sitemap = Sitemap::parse(&String::from_utf8_lossy(&body));
+            Ok((url, sitemap))
+        }
+        .boxed()
+    }
+}
+
+/// Decompresses gzipped sitemaps, e.g. `sitemap.xml.gz`, which servers send
+/// as is rather than with a content encoding
+fn gunzip(body: Vec<u8>) -> Result<Vec<u8>> {
+    if !body.starts_with(&[0x1f, 0x8b]) {
+        return Ok(body);
+    }
+    let mut ret = Vec::new();
+    GzDecoder::new(&body[..])
+        .take(SIZE_LIMIT as u64)
+        .read_to_end(&mut ret)
+        .map_err(ConveyorError::new)?;
+    Ok(ret)
+}
+
+impl Stream for SitemapProducer {
+    type Item = Result<HttpOptions>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = Pin::get_mut(self);
+
+        loop {
+            if let Some(url) = this.urls.pop_front() {
+                return Poll::Ready(Some(Ok(HttpOptions::new(Method::GET, url))));
+            }
+
+            if let Some(fetching) = this.fetching.as_mut() {
+                let (base, sitemap) = match fetching.poll_unpin(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(ret) => {
+                        this.fetching = None;
+                        match ret {
+                            Ok(ret) => ret,
+                            Err(e) => return Poll::Ready(Some(Err(e))),
+                        }
+                    }
+                };
+                // locations should be absolute, but relative ones are common enough
+                let resolve = |m: &String| base.join(m).ok();
+                this.urls.extend(sitemap.urls.iter().filter_map(resolve));
+                for url in sitemap.sitemaps.iter().filter_map(resolve) {
+                    if this.seen.insert(url.clone()) {
+                        this.sitemaps.push_back(url);
+                    }
+                }
+                continue;
+            }
+
+            match this.sitemaps.pop_front() {
+                Some(url) => this.fetching = Some(this.fetch(url)),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conveyor_http::testing::{response, serve_fn};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn parse() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"
+                    xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+              <url><loc>https://example.com/?a=1&amp;b=2</loc><lastmod>2024-01-01</lastmod></url>
+              <url>
+                <loc> <![CDATA[https://example.com/about]]> </loc>
+                <image:image><image:loc>https://example.com/a.png</image:loc></image:image>
+              </url>
+            </urlset>"#;
+        assert_eq!(
+            Sitemap::parse(xml).urls,
+            vec!["https://example.com/?a=1&b=2", "https://example.com/about"]
+        );
+
+        let index =
+            "<sitemapindex><sitemap><loc>https://example.com/a.xml</loc></sitemap></sitemapindex>";
+        assert_eq!(
+            Sitemap::parse(index),
+            Sitemap {
+                urls: Vec::new(),
+                sitemaps: vec!["https://example.com/a.xml".to_string()],
+            }
+        );
+
+        let text = "https://example.com/a\r\n\r\nhttps://example.com/b\n";
+        assert_eq!(Sitemap::parse(text).urls.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn producer() {
+        let url = serve_fn(|request| {
+            let path = request.split_whitespace().nth(1).unwrap_or("");
+            let body = match path {
+                "/index.xml" => {
+                    "<sitemapindex>\
+                    <sitemap><loc>/pages.xml</loc></sitemap>\
+                    <sitemap><loc>/missing.xml</loc></sitemap>\
+                    <sitemap><loc>/index.xml</loc></sitemap>\
+                    </sitemapindex>"
+                }
+                "/pages.xml" => "<urlset><url><loc>/a</loc></url><url><loc>/b</loc></url></urlset>",
+                "/pages.xml.gz" => {
+                    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+                    gz.write_all(b"<urlset><url><loc>/c</loc></url></urlset>")
+                        .unwrap();
+                    let body = gz.finish().unwrap();
+                    return response("200 OK", &[("content-type", "application/gzip")], &body);
+                }
+                _ => return response("404 Not Found", &[], b""),
+            };
+            response("200 OK", &[], body.as_bytes())
+        });
+
+        let producer = SitemapProducer::new(vec![url.join("/index.xml").unwrap()]);
+        let ret = producer.collect::<Vec<_>>().await;
+        assert_eq!(ret.len(), 3);
+        assert_eq!(ret[0].as_ref().unwrap().url, url.join("/a").unwrap());
+        assert_eq!(ret[1].as_ref().unwrap().url, url.join("/b").unwrap());
+        assert!(ret[2].is_err());
+
+        let robots = Robots::parse(&format!("Sitemap: {}pages.xml", url), "bot");
+        let ret = SitemapProducer::from_robots(&robots)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(ret.len(), 2);
+
+        let producer = SitemapProducer::new(vec![url.join("/pages.xml.gz").unwrap()]);
+        let ret = producer.collect::<Vec<_>>().await;
+        assert_eq!(ret[0].as_ref().unwrap().url, url.join("/c").unwrap());
+    }
+}
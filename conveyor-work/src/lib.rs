@@ -1,5 +1,3 @@
-#![feature(async_await, async_closure)]
-
 #[macro_use]
 extern crate serde_derive;
 
@@ -11,41 +9,77 @@ pub mod fs;
 #[cfg(feature = "http")]
 pub mod http;
 pub mod package;
+#[cfg(feature = "http")]
+mod serde_headers;
+#[cfg(feature = "http")]
+mod serde_millis;
 pub mod traits;
 pub mod utils;
 
 pub mod producers {
 
     #[cfg(feature = "http")]
-    use super::http::{HttpOptions, HttpProducer};
+    use super::http::{HttpOptions, HttpProducer, SitemapProducer};
 
     #[cfg(feature = "http")]
     pub fn http(options: Vec<HttpOptions>) -> HttpProducer {
         HttpProducer::new(options)
     }
 
+    #[cfg(feature = "http")]
+    pub fn sitemap(sitemaps: Vec<url::Url>) -> SitemapProducer {
+        SitemapProducer::new(sitemaps)
+    }
+
     #[cfg(feature = "fs")]
     pub mod fs {
         use super::super::fs::{FileProducer, FS};
+        use conveyor::{Result, Spawner};
         use vfs::{ReadPath, VFS};
 
-        pub fn glob<V: 'static, S: AsRef<str>>(fs: V, glob: S) -> FileProducer<FS<V>, V::Path>
+        pub fn glob<V, S: AsRef<str>>(fs: V, glob: S) -> FileProducer<FS<V>, V::Path>
         where
-            V: VFS,
+            V: VFS + 'static,
             <V as VFS>::Path: ReadPath,
         {
             let fs = FS::glob(fs, glob);
             FileProducer::new(fs, 2, true)
         }
 
-        pub fn path<V: 'static, S: AsRef<str>>(fs: V, path: S) -> FileProducer<FS<V>, V::Path>
+        pub fn glob_with<Sp: Spawner + ?Sized, V, S: AsRef<str>>(
+            spawner: &Sp,
+            fs: V,
+            glob: S,
+        ) -> Result<FileProducer<FS<V>, V::Path>>
         where
-            V: VFS,
+            V: VFS + 'static,
+            <V as VFS>::Path: ReadPath,
+        {
+            let fs = FS::glob_with(spawner, fs, glob)?;
+            FileProducer::with_spawner(spawner, fs, 2, true)
+        }
+
+        pub fn path<V, S: AsRef<str>>(fs: V, path: S) -> FileProducer<FS<V>, V::Path>
+        where
+            V: VFS + 'static,
             <V as VFS>::Path: ReadPath,
         {
             let fs = FS::path(fs, path);
             FileProducer::new(fs, 2, true)
         }
+
+        pub fn path_with<Sp: Spawner + ?Sized, V, S: AsRef<str>>(
+            spawner: &Sp,
+            fs: V,
+            path: S,
+        ) -> Result<FileProducer<FS<V>, V::Path>>
+        where
+            V: VFS + 'static,
+            <V as VFS>::Path: ReadPath,
+        {
+            let fs = FS::path_with(spawner, fs, path)?;
+            FileProducer::with_spawner(spawner, fs, 2, true)
+        }
     }
 }
 
@@ -53,5 +87,4 @@ pub mod prelude {
     pub use super::package::Package;
     pub use super::producers as pro;
     //pub use super::work::*;
-
 }
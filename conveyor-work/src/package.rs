@@ -1,5 +1,6 @@
 use conveyor::futures::prelude::*;
 use conveyor::{ConveyorError, OneOf4Future, Promise4};
+use pin_project::pin_project;
 use serde_json::{self, Value};
 use std::fmt;
 use std::io::Read;
@@ -9,8 +10,8 @@ use std::task::{Context, Poll};
 
 pub enum PackageContent {
     Bytes(Vec<u8>),
-    Stream(Pin<Box<Stream<Item = Result<Vec<u8>>> + Send>>),
-    Reader(Box<Read + Send>),
+    Stream(Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>),
+    Reader(Box<dyn Read + Send>),
     Empty,
 }
 
@@ -54,10 +55,12 @@ impl IntoPackageContent for PackageContent {
     }
 }
 
+#[pin_project]
 pub struct ConcatStream<Str, V>
 where
     Str: Stream<Item = Result<Vec<V>>>,
 {
+    #[pin]
     stream: Str,
     values: Vec<V>,
 }
@@ -81,14 +84,14 @@ where
     type Output = Result<Vec<V>>;
 
     fn poll(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Self::Output> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let mut this = self.project();
 
         loop {
-            match unsafe { Pin::new_unchecked(&mut this.stream) }.poll_next(waker) {
+            match this.stream.as_mut().poll_next(waker) {
                 Poll::Pending => return Poll::Pending,
                 Poll::Ready(o) => match o {
                     None => {
-                        let values = std::mem::replace(&mut this.values, Vec::new());
+                        let values = std::mem::take(this.values);
                         return Poll::Ready(Ok(values));
                     }
                     Some(s) => match s {
@@ -121,18 +124,18 @@ impl IntoPackageContent for &[u8] {
 
 impl IntoPackageContent for String {
     fn into_package_content(self) -> PackageContent {
-        PackageContent::Bytes(self.as_str().as_bytes().to_vec())
+        PackageContent::Bytes(self.into_bytes())
     }
 }
 
-impl<'a> IntoPackageContent for &'a str {
+impl IntoPackageContent for &str {
     fn into_package_content(self) -> PackageContent {
         PackageContent::Bytes(self.as_bytes().to_vec())
     }
 }
 
 impl IntoPackageContent
-    for Pin<Box<conveyor::futures::stream::Stream<Item = Result<Vec<u8>>> + Send>>
+    for Pin<Box<dyn conveyor::futures::stream::Stream<Item = Result<Vec<u8>>> + Send>>
 {
     fn into_package_content(self) -> PackageContent {
         PackageContent::Stream(self)
@@ -196,6 +199,14 @@ impl Package {
         self
     }
 
+    /// Takes the content, e.g. to stream it somewhere without buffering it
+    pub fn into_content(self) -> PackageContent {
+        self.value
+            .into_inner()
+            .unwrap()
+            .unwrap_or(PackageContent::Empty)
+    }
+
     pub fn read_content(&mut self) -> impl Future<Output = Result<Vec<u8>>> {
         // let body = mem::replace(self.value.lock().unwrap().body_mut(), Decoder::empty());
         let content = std::mem::replace(
@@ -0,0 +1,45 @@
+use conveyor_http::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde::ser::{Error as SerError, SerializeMap, Serializer};
+use std::collections::BTreeMap;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+pub fn serialize<S: Serializer>(headers: &HeaderMap, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(headers.keys_len()))?;
+    for key in headers.keys() {
+        let values = headers
+            .get_all(key)
+            .iter()
+            .map(|v| v.to_str().map_err(S::Error::custom))
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.len() == 1 {
+            map.serialize_entry(key.as_str(), values[0])?;
+        } else {
+            map.serialize_entry(key.as_str(), &values)?;
+        }
+    }
+    map.end()
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HeaderMap, D::Error> {
+    let raw = BTreeMap::<String, OneOrMany>::deserialize(deserializer)?;
+    let mut headers = HeaderMap::new();
+    for (key, values) in raw {
+        let name = HeaderName::from_bytes(key.as_bytes()).map_err(D::Error::custom)?;
+        let values = match values {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        };
+        for value in values {
+            let value = HeaderValue::from_str(&value).map_err(D::Error::custom)?;
+            headers.append(name.clone(), value);
+        }
+    }
+    Ok(headers)
+}
@@ -1,9 +1,10 @@
 use super::package::Package;
 use conveyor::futures::prelude::*;
 use conveyor::{OneOfFuture, Promise, Result, Station};
+use pin_project::pin_project;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::task::{Poll, Context};
+use std::task::{Context, Poll};
 
 pub trait ChainStreamer: Stream + Sized
 where
@@ -25,7 +26,9 @@ where
     }
 }
 
+#[pin_project]
 pub struct ChainStream<Str, St> {
+    #[pin]
     stream: Str,
     station: Arc<St>,
 }
@@ -36,12 +39,12 @@ where
     <Str as Stream>::Item: Future<Output = Result<Package>> + 'static + Send,
     St: Station<Input = Package, Output = Package> + 'static + Send + Sync,
 {
-    type Item = Pin<Box<Future<Output = Result<Package>> + Send>>;
+    type Item = Pin<Box<dyn Future<Output = Result<Package>> + Send>>;
 
     fn poll_next(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Option<Self::Item>> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let this = self.project();
 
-        match unsafe { Pin::new_unchecked(&mut this.stream) }.poll_next(waker) {
+        match this.stream.poll_next(waker) {
             Poll::Pending => Poll::Pending,
             Poll::Ready(ret) => match ret {
                 None => Poll::Ready(None),
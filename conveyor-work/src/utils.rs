@@ -1,40 +1,25 @@
 use super::package::Package;
 use conveyor::futures::prelude::*;
 use conveyor::{ConveyorError, Result, Station};
+use pin_project::pin_project;
 use std::pin::Pin;
-use std::task::{Poll, Context};
+use std::task::{Context, Poll};
 
 pub type BoxedStation = Box<
-    Station<
+    dyn Station<
             Input = Package,
             Output = Package,
-            Future = Pin<Box<Future<Output = Result<Package>> + Send>>,
+            Future = Pin<Box<dyn Future<Output = Result<Package>> + Send>>,
         > + Send
         + Sync,
 >;
 
 pub struct BoxWrap {
-    inner: Box<
-        Station<
-                Input = Package,
-                Output = Package,
-                Future = Pin<Box<Future<Output = Result<Package>> + Send>>,
-            > + Send
-            + Sync,
-    >,
+    inner: BoxedStation,
 }
 
 impl BoxWrap {
-    pub fn new(
-        station: Box<
-            Station<
-                    Input = Package,
-                    Output = Package,
-                    Future = Pin<Box<Future<Output = Result<Package>> + Send>>,
-                > + Send
-                + Sync,
-        >,
-    ) -> BoxWrap {
+    pub fn new(station: BoxedStation) -> BoxWrap {
         BoxWrap { inner: station }
     }
 }
@@ -42,24 +27,27 @@ impl BoxWrap {
 impl Station for BoxWrap {
     type Input = Package;
     type Output = Package;
-    type Future = Pin<Box<Future<Output = Result<Package>> + Send>>;
+    type Future = Pin<Box<dyn Future<Output = Result<Package>> + Send>>;
     fn execute(&self, input: Self::Input) -> Self::Future {
         self.inner.execute(input)
     }
 }
 
+#[pin_project(project = FutureOrErrProj)]
 pub enum FutureOrErr<F, V>
 where
     F: Future<Output = Result<V>>,
 {
     Err(Option<ConveyorError>),
-    Future(F),
+    Future(#[pin] F),
 }
 
+#[pin_project]
 pub struct FutureOrErrFuture<F, V>
 where
     F: Future<Output = Result<V>>,
 {
+    #[pin]
     inner: FutureOrErr<F, V>,
 }
 
@@ -91,11 +79,9 @@ where
 {
     type Output = Result<V>;
     fn poll(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Self::Output> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
-
-        match &mut this.inner {
-            FutureOrErr::Err(e) => Poll::Ready(Err(e.take().unwrap())),
-            FutureOrErr::Future(f) => unsafe { Pin::new_unchecked(f) }.poll(waker),
+        match self.project().inner.project() {
+            FutureOrErrProj::Err(e) => Poll::Ready(Err(e.take().unwrap())),
+            FutureOrErrProj::Future(f) => f.poll(waker),
         }
     }
 }
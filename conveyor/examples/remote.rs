@@ -0,0 +1,40 @@
+//! Splits numbers over agent processes.
+//!
+//!     cargo run --example remote --features remote -- coordinator 127.0.0.1:7878
+//!     cargo run --example remote --features remote -- agent 127.0.0.1:7878
+
+use conveyor::work::{Agent, RemoteConfig, RemoteWorker, Work, WorkOutput, Worker};
+use conveyor::{station_fn, Result};
+use futures::executor::block_on;
+
+fn main() -> Result<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let addr = args.get(2).map(|m| m.as_str()).unwrap_or("127.0.0.1:7878");
+
+    match args.get(1).map(|m| m.as_str()) {
+        Some("agent") => {
+            let mut agent = Agent::new(4, RemoteConfig::default());
+            agent.register(
+                "split",
+                station_fn(|val: u64| async move {
+                    println!("splitting {}", val);
+                    let mut out = vec![WorkOutput::Result(val)];
+                    if val > 1 {
+                        out.push(WorkOutput::Work(Work::named(val / 2, "split")));
+                        out.push(WorkOutput::Work(Work::named(val - val / 2, "split")));
+                    }
+                    Ok(out)
+                }),
+            );
+            agent.connect_tcp(addr)
+        }
+        _ => {
+            let remote = RemoteWorker::bind_tcp(addr, RemoteConfig::default())?;
+            let mut worker = Worker::default();
+            worker.register("split", remote.station("split"));
+            let ret = block_on(worker.run(vec![Work::named(64, "split")]));
+            println!("{} results", ret.len());
+            Ok(())
+        }
+    }
+}
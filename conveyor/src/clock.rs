@@ -0,0 +1,176 @@
+use futures::channel::oneshot;
+use futures::future::{ready, BoxFuture};
+use futures::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// The time source of scheduled producers and worker time budgets
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+    fn sleep_until(&self, at: SystemTime) -> BoxFuture<'static, ()>;
+}
+
+/// The system's wall clock. Sleeps are woken by a timer thread shared by
+/// all of them, started on the first sleep.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep_until(&self, at: SystemTime) -> BoxFuture<'static, ()> {
+        let duration = match at.duration_since(SystemTime::now()) {
+            Ok(duration) => duration,
+            Err(_) => return ready(()).boxed(),
+        };
+        Timer::get().sleep(Instant::now() + duration)
+    }
+}
+
+#[derive(Default)]
+struct Sleeping {
+    /// Keyed by when to wake up, and a sequence number to keep them apart
+    sleepers: BTreeMap<(Instant, u64), oneshot::Sender<()>>,
+    seq: u64,
+    /// The number of sleepers after the last sweep of cancelled sleeps
+    swept: usize,
+}
+
+struct Timer {
+    sleeping: Mutex<Sleeping>,
+    changed: Condvar,
+}
+
+impl Timer {
+    fn get() -> &'static Timer {
+        static TIMER: OnceLock<&'static Timer> = OnceLock::new();
+        TIMER.get_or_init(|| {
+            let timer: &'static Timer = Box::leak(Box::new(Timer {
+                sleeping: Mutex::new(Sleeping::default()),
+                changed: Condvar::new(),
+            }));
+            thread::Builder::new()
+                .name("conveyor-timer".to_string())
+                .spawn(move || timer.run())
+                .expect("could not spawn timer thread");
+            timer
+        })
+    }
+
+    fn sleep(&self, at: Instant) -> BoxFuture<'static, ()> {
+        let (wake, rx) = oneshot::channel();
+        let mut sleeping = self.sleeping.lock().unwrap();
+        // drop sleeps nobody waits for anymore, e.g. budgets of finished work,
+        // whenever their number doubled
+        if sleeping.sleepers.len() >= sleeping.swept.max(16) * 2 {
+            sleeping.sleepers.retain(|_, m| !m.is_canceled());
+            sleeping.swept = sleeping.sleepers.len();
+        }
+        sleeping.seq += 1;
+        let seq = sleeping.seq;
+        let first = sleeping.sleepers.keys().next().is_none_or(|m| at < m.0);
+        sleeping.sleepers.insert((at, seq), wake);
+        if first {
+            self.changed.notify_one();
+        }
+        rx.map(|_| ()).boxed()
+    }
+
+    fn run(&self) {
+        let mut sleeping = self.sleeping.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            while let Some(entry) = sleeping.sleepers.first_entry() {
+                if entry.key().0 > now {
+                    break;
+                }
+                let _ = entry.remove().send(());
+            }
+            sleeping = match sleeping.sleepers.keys().next() {
+                Some(&(at, _)) => self.changed.wait_timeout(sleeping, at - now).unwrap().0,
+                None => self.changed.wait(sleeping).unwrap(),
+            };
+        }
+    }
+}
+
+struct Sleeper {
+    at: SystemTime,
+    wake: oneshot::Sender<()>,
+}
+
+struct Manual {
+    now: SystemTime,
+    sleepers: Vec<Sleeper>,
+}
+
+/// A clock which only moves when told to, for deterministic tests.
+/// Clones share the same time.
+#[derive(Clone)]
+pub struct ManualClock {
+    inner: Arc<Mutex<Manual>>,
+}
+
+impl ManualClock {
+    pub fn new(now: SystemTime) -> ManualClock {
+        ManualClock {
+            inner: Arc::new(Mutex::new(Manual {
+                now,
+                sleepers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Moves the time forward, waking everything sleeping until then
+    pub fn advance(&self, duration: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.now += duration;
+        let now = inner.now;
+        let (woken, sleeping) = inner.sleepers.drain(..).partition(|m| m.at <= now);
+        inner.sleepers = sleeping;
+        for sleeper in woken {
+            let _ = sleeper.wake.send(());
+        }
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        self.inner.lock().unwrap().now
+    }
+
+    fn sleep_until(&self, at: SystemTime) -> BoxFuture<'static, ()> {
+        let mut inner = self.inner.lock().unwrap();
+        if at <= inner.now {
+            return ready(()).boxed();
+        }
+        let (wake, rx) = oneshot::channel();
+        inner.sleepers.push(Sleeper { at, wake });
+        rx.map(|_| ()).boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::future::{select, Either};
+
+    #[test]
+    fn system_clock() {
+        let clock = SystemClock;
+        let now = clock.now();
+        let late = clock.sleep_until(now + Duration::from_millis(200));
+        let early = clock.sleep_until(now + Duration::from_millis(20));
+        drop(clock.sleep_until(now + Duration::from_millis(10)));
+        match block_on(select(late, early)) {
+            Either::Right((_, late)) => block_on(late),
+            Either::Left(_) => panic!("woke the later sleep first"),
+        }
+        assert!(clock.now() >= now + Duration::from_millis(200));
+    }
+}
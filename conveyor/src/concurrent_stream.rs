@@ -1,16 +1,21 @@
 use futures::prelude::*;
-use std::collections::VecDeque;
+use futures::stream::FuturesUnordered;
+use pin_project::pin_project;
 use std::pin::Pin;
-use std::task::{Poll, Context};
+use std::task::{Context, Poll};
 
+/// Polls up to `max` futures from the underlying stream at once (unbounded
+/// when `max` is 0), yielding their outputs in order of completion.
+#[pin_project]
 pub struct ConcurrentStream<S>
 where
     S: Stream,
     <S as Stream>::Item: Future,
 {
+    #[pin]
     s: S,
-    p: Option<Vec<S::Item>>,
-    d: VecDeque<<<S as Stream>::Item as Future>::Output>,
+    p: FuturesUnordered<S::Item>,
+    done: bool,
     max: usize,
 }
 
@@ -22,8 +27,8 @@ where
     pub fn new(stream: S, max: usize) -> ConcurrentStream<S> {
         ConcurrentStream {
             s: stream,
-            p: Some(Vec::new()),
-            d: VecDeque::new(),
+            p: FuturesUnordered::new(),
+            done: false,
             max,
         }
     }
@@ -37,49 +42,47 @@ where
     type Item = <<S as Stream>::Item as Future>::Output;
 
     fn poll_next(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Option<Self::Item>> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let mut this = self.project();
 
-        if this.max == 0 || this.p.as_ref().unwrap().len() < this.max {
-            let next = match unsafe { Pin::new_unchecked(&mut this.s) }.poll_next(waker) {
-                Poll::Pending => {
-                    if this.p.as_ref().unwrap().is_empty() {
-                        return Poll::Pending;
-                    }
-                    None
-                }
-                Poll::Ready(s) => s,
-            };
-
-            if let Some(s) = next {
-                this.p.as_mut().unwrap().push(s);
+        while !*this.done && (*this.max == 0 || this.p.len() < *this.max) {
+            match this.s.as_mut().poll_next(waker) {
+                Poll::Ready(Some(fut)) => this.p.push(fut),
+                Poll::Ready(None) => *this.done = true,
+                Poll::Pending => break,
             }
         }
 
-        if !this.p.as_ref().unwrap().is_empty() {
-            let iter = this.p.take().unwrap().into_iter();
+        match Pin::new(this.p).poll_next(waker) {
+            Poll::Ready(None) if !*this.done => Poll::Pending,
+            ret => ret,
+        }
+    }
+}
 
-            let ret = iter
-                .filter_map(
-                    |mut i| match unsafe { Pin::new_unchecked(&mut i) }.poll(waker) {
-                        Poll::Pending => Some(i),
-                        Poll::Ready(m) => {
-                            this.d.push_back(m);
-                            None
-                        }
-                    },
-                )
-                .collect::<Vec<_>>();
+#[cfg(test)]
+mod tests {
+    use super::super::futures_utils::tests::Pinned;
+    use super::*;
+    use futures::executor::block_on;
 
-            this.p = Some(ret);
-        }
-        if this.p.as_ref().unwrap().is_empty() && this.d.is_empty() {
-            return Poll::Ready(None);
-        }
+    #[test]
+    fn not_unpin_futures() {
+        let stream = futures::stream::iter(0..10).map(|i| async move {
+            let v = vec![i];
+            let r = &v;
+            Pinned::new(()).await;
+            r[0] * 2
+        });
 
-        if this.d.is_empty() {
-            return Poll::Pending;
-        }
+        let mut ret = block_on(ConcurrentStream::new(stream, 3).collect::<Vec<_>>());
+        ret.sort();
+        assert_eq!(ret, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+    }
 
-        Poll::Ready(this.d.pop_front())
+    #[test]
+    fn unbounded() {
+        let stream = futures::stream::iter(0..5).map(Pinned::new);
+        let ret = block_on(ConcurrentStream::new(stream, 0).collect::<Vec<_>>());
+        assert_eq!(ret.len(), 5);
     }
 }
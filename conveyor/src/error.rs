@@ -14,6 +14,11 @@ impl ConveyorError {
             inner: Box::new(error),
         }
     }
+
+    /// The underlying error, if it's an `E`
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        self.inner.downcast_ref::<E>()
+    }
 }
 
 impl fmt::Debug for ConveyorError {
@@ -24,7 +29,7 @@ impl fmt::Debug for ConveyorError {
 
 impl fmt::Display for ConveyorError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.inner.to_string())
+        write!(f, "{}", self.inner)
     }
 }
 
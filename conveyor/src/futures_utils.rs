@@ -1,13 +1,17 @@
 use futures::prelude::*;
+use pin_project::pin_project;
 use std::pin::Pin;
-use std::task::{Poll, Context};
+use std::task::{Context, Poll};
 
+#[pin_project(project = PromiseProj)]
 pub enum Promise<T1, T2> {
-    First(T1),
-    Second(T2),
+    First(#[pin] T1),
+    Second(#[pin] T2),
 }
 
+#[pin_project]
 pub struct OneOfFuture<T1: Future<Output = V>, T2: Future<Output = V>, V> {
+    #[pin]
     inner: Promise<T1, T2>,
 }
 
@@ -20,22 +24,23 @@ impl<T1: Future<Output = V>, T2: Future<Output = V>, V> OneOfFuture<T1, T2, V> {
 impl<T1: Future<Output = V>, T2: Future<Output = V>, V> Future for OneOfFuture<T1, T2, V> {
     type Output = V;
     fn poll(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Self::Output> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
-
-        match &mut this.inner {
-            Promise::First(fut) => unsafe { Pin::new_unchecked(fut) }.poll(waker),
-            Promise::Second(fut) => unsafe { Pin::new_unchecked(fut) }.poll(waker),
+        match self.project().inner.project() {
+            PromiseProj::First(fut) => fut.poll(waker),
+            PromiseProj::Second(fut) => fut.poll(waker),
         }
     }
 }
 
+#[pin_project(project = Promise3Proj)]
 pub enum Promise3<T1, T2, T3> {
-    First(T1),
-    Second(T2),
-    Third(T3),
+    First(#[pin] T1),
+    Second(#[pin] T2),
+    Third(#[pin] T3),
 }
 
+#[pin_project]
 pub struct OneOf3Future<T1: Future<Output = V>, T2: Future<Output = V>, T3: Future<Output = V>, V> {
+    #[pin]
     inner: Promise3<T1, T2, T3>,
 }
 
@@ -52,23 +57,23 @@ impl<T1: Future<Output = V>, T2: Future<Output = V>, T3: Future<Output = V>, V>
 {
     type Output = V;
     fn poll(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Self::Output> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
-
-        match &mut this.inner {
-            Promise3::First(fut) => unsafe { Pin::new_unchecked(fut) }.poll(waker),
-            Promise3::Second(fut) => unsafe { Pin::new_unchecked(fut) }.poll(waker),
-            Promise3::Third(fut) => unsafe { Pin::new_unchecked(fut) }.poll(waker),
+        match self.project().inner.project() {
+            Promise3Proj::First(fut) => fut.poll(waker),
+            Promise3Proj::Second(fut) => fut.poll(waker),
+            Promise3Proj::Third(fut) => fut.poll(waker),
         }
     }
 }
 
+#[pin_project(project = Promise4Proj)]
 pub enum Promise4<T1, T2, T3, T4> {
-    First(T1),
-    Second(T2),
-    Third(T3),
-    Fourth(T4),
+    First(#[pin] T1),
+    Second(#[pin] T2),
+    Third(#[pin] T3),
+    Fourth(#[pin] T4),
 }
 
+#[pin_project]
 pub struct OneOf4Future<
     T1: Future<Output = V>,
     T2: Future<Output = V>,
@@ -76,6 +81,7 @@ pub struct OneOf4Future<
     T4: Future<Output = V>,
     V,
 > {
+    #[pin]
     inner: Promise4<T1, T2, T3, T4>,
 }
 
@@ -102,13 +108,74 @@ impl<
 {
     type Output = V;
     fn poll(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Self::Output> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
+        match self.project().inner.project() {
+            Promise4Proj::First(fut) => fut.poll(waker),
+            Promise4Proj::Second(fut) => fut.poll(waker),
+            Promise4Proj::Third(fut) => fut.poll(waker),
+            Promise4Proj::Fourth(fut) => fut.poll(waker),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::marker::PhantomPinned;
+
+    /// A `!Unpin` future which is pending once before resolving
+    #[pin_project]
+    pub struct Pinned<V> {
+        value: Option<V>,
+        yielded: bool,
+        #[pin]
+        _pin: PhantomPinned,
+    }
 
-        match &mut this.inner {
-            Promise4::First(fut) => unsafe { Pin::new_unchecked(fut) }.poll(waker),
-            Promise4::Second(fut) => unsafe { Pin::new_unchecked(fut) }.poll(waker),
-            Promise4::Third(fut) => unsafe { Pin::new_unchecked(fut) }.poll(waker),
-            Promise4::Fourth(fut) => unsafe { Pin::new_unchecked(fut) }.poll(waker),
+    impl<V> Pinned<V> {
+        pub fn new(value: V) -> Pinned<V> {
+            Pinned {
+                value: Some(value),
+                yielded: false,
+                _pin: PhantomPinned,
+            }
         }
     }
+
+    impl<V> Future for Pinned<V> {
+        type Output = V;
+        fn poll(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Self::Output> {
+            let this = self.project();
+            if !*this.yielded {
+                *this.yielded = true;
+                waker.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            Poll::Ready(this.value.take().expect("polled after completion"))
+        }
+    }
+
+    #[test]
+    fn one_of_not_unpin() {
+        let fut = OneOfFuture::new(Promise::<_, future::Ready<i32>>::First(Pinned::new(1)));
+        assert_eq!(block_on(fut), 1);
+
+        let fut = OneOf3Future::new(Promise3::<future::Ready<i32>, _, Pinned<i32>>::Second(
+            async {
+                let v = vec![2];
+                let r = &v;
+                Pinned::new(()).await;
+                r[0]
+            },
+        ));
+        assert_eq!(block_on(fut), 2);
+
+        let fut = OneOf4Future::new(Promise4::<
+            future::Ready<i32>,
+            future::Ready<i32>,
+            future::Ready<i32>,
+            _,
+        >::Fourth(Pinned::new(4)));
+        assert_eq!(block_on(fut), 4);
+    }
 }
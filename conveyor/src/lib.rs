@@ -1,26 +1,24 @@
-//#![feature(async_await, await_macro, futures_api)]
-#![feature(async_await,async_closure)]
-
+mod clock;
 mod concurrent_stream;
 mod error;
 mod futures_utils;
 mod macros;
+mod spawner;
 mod traits;
 pub mod utils;
 mod work_station;
 pub use futures;
 
-pub use futures_old;
-
 #[cfg(feature = "producer")]
 pub mod producer;
 
 #[cfg(feature = "work")]
 pub mod work;
 
+pub use clock::*;
 pub use concurrent_stream::*;
 pub use error::*;
 pub use futures_utils::*;
-pub use macros::*;
+pub use spawner::*;
 pub use traits::*;
 pub use work_station::*;
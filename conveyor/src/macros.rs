@@ -26,10 +26,10 @@ mod tests {
     #[test]
     fn test_conveyor() {
         let chain = conveyor![
-            station_fn(async move |s: &str| Ok(s)),
-            station_fn(async move |s: &str| Ok(s)),
-            conveyor![station_fn(async move |s: &str| Ok(s))],
-            conveyor![station_fn(async move |s: &str| Ok(s))]
+            station_fn(|s: &'static str| async move { Ok(s) }),
+            station_fn(|s: &'static str| async move { Ok(s) }),
+            conveyor![station_fn(|s: &'static str| async move { Ok(s) })],
+            conveyor![station_fn(|s: &'static str| async move { Ok(s) })]
         ];
 
         let result = futures::executor::block_on(chain.execute("Hello, World!")).unwrap();
@@ -39,13 +39,12 @@ mod tests {
     #[test]
     fn test_conveyor_meaning_of_life() {
         let chain = conveyor![
-            station_fn(async move |input: &str| Ok(input.len())),
-            station_fn(async move |len: usize| Ok(len * 7))
+            station_fn(|input: &'static str| async move { Ok(input.len()) }),
+            station_fn(|len: usize| async move { Ok(len * 7) })
         ];
 
         let ans = futures::executor::block_on(chain.execute("Hello!"));
 
         assert_eq!(ans.unwrap(), 42);
     }
-
 }
@@ -0,0 +1,235 @@
+use super::super::{ConveyorError, Result};
+use super::Schedule;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+pub struct CronError {
+    expression: String,
+    reason: String,
+}
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid cron expression '{}': {}",
+            self.expression, self.reason
+        )
+    }
+}
+
+impl Error for CronError {}
+
+/// A set of allowed values of a field, as bits
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Field(u64);
+
+impl Field {
+    fn parse(field: &str, min: u64, max: u64) -> std::result::Result<Field, String> {
+        let mut bits = 0;
+        for part in field.split(',') {
+            let (range, step) = match part.find('/') {
+                Some(idx) => {
+                    let step = part[idx + 1..]
+                        .parse::<u64>()
+                        .map_err(|_| format!("invalid step in '{}'", part))?;
+                    if step == 0 {
+                        return Err(format!("invalid step in '{}'", part));
+                    }
+                    (&part[..idx], step)
+                }
+                None => (part, 1),
+            };
+            let (from, to) = if range == "*" {
+                (min, max)
+            } else if let Some(idx) = range.find('-') {
+                (
+                    parse_value(&range[..idx], min, max)?,
+                    parse_value(&range[idx + 1..], min, max)?,
+                )
+            } else {
+                let from = parse_value(range, min, max)?;
+                // "5/15" runs from 5 to the end
+                (from, if step > 1 { max } else { from })
+            };
+            if from > to {
+                return Err(format!("invalid range '{}'", range));
+            }
+            bits |= (from..=to)
+                .step_by(step as usize)
+                .fold(0, |m, v| m | 1 << v);
+        }
+        Ok(Field(bits))
+    }
+
+    fn contains(self, value: u64) -> bool {
+        self.0 & (1 << value) != 0
+    }
+}
+
+fn parse_value(value: &str, min: u64, max: u64) -> std::result::Result<u64, String> {
+    match value.parse::<u64>() {
+        Ok(v) if v >= min && v <= max => Ok(v),
+        _ => Err(format!("'{}' is not within {}-{}", value, min, max)),
+    }
+}
+
+/// A standard five field cron expression: minute, hour, day of month, month and day of week.
+///
+/// Fields take `*`, values, ranges (`1-5`), steps (`*/15`) and lists (`0,30`).
+/// Also understands `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly`.
+/// Times are in UTC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+    /// Whether days or weekdays is `*`. If neither is, a day matching either one is enough.
+    any_day: bool,
+}
+
+impl Cron {
+    pub fn parse(expression: &str) -> Result<Cron> {
+        Cron::parse_fields(expression).map_err(|reason| {
+            ConveyorError::new(CronError {
+                expression: expression.to_string(),
+                reason,
+            })
+        })
+    }
+
+    fn parse_fields(expression: &str) -> std::result::Result<Cron, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(format!("expected 5 fields, got {}", fields.len()));
+        }
+
+        let mut weekdays = Field::parse(fields[4], 0, 7)?;
+        // both 0 and 7 are sunday
+        if weekdays.contains(7) {
+            weekdays.0 |= 1;
+        }
+        Ok(Cron {
+            minutes: Field::parse(fields[0], 0, 59)?,
+            hours: Field::parse(fields[1], 0, 23)?,
+            days: Field::parse(fields[2], 1, 31)?,
+            months: Field::parse(fields[3], 1, 12)?,
+            weekdays,
+            any_day: fields[2] == "*" || fields[4] == "*",
+        })
+    }
+
+    fn matches_day(&self, days: u64) -> bool {
+        let (_, month, day) = civil_from_days(days);
+        if !self.months.contains(month) {
+            return false;
+        }
+        // 1970-01-01 was a thursday
+        let weekday = (days + 4) % 7;
+        if self.any_day {
+            self.days.contains(day) && self.weekdays.contains(weekday)
+        } else {
+            self.days.contains(day) || self.weekdays.contains(weekday)
+        }
+    }
+}
+
+impl Schedule for Cron {
+    fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|m| m.as_secs())
+            .unwrap_or(0);
+        let start = secs / 60 + 1;
+        let (mut days, mut from) = (start / 1440, start % 1440);
+
+        // every valid expression matches within a few years
+        for _ in 0..366 * 8 {
+            if self.matches_day(days) {
+                let minute = (from..1440)
+                    .find(|m| self.hours.contains(m / 60) && self.minutes.contains(m % 60));
+                if let Some(minute) = minute {
+                    return Some(UNIX_EPOCH + Duration::from_secs((days * 1440 + minute) * 60));
+                }
+            }
+            days += 1;
+            from = 0;
+        }
+        None
+    }
+}
+
+/// Year, month and day of a count of days since 1970-01-01
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn next_after() {
+        // 2024-02-28 23:50:30, a wednesday
+        let time = at(1_709_164_230);
+
+        let cron = Cron::parse("*/15 * * * *").unwrap();
+        assert_eq!(cron.next_after(time), Some(at(1_709_164_800)));
+
+        // leap day
+        let cron = Cron::parse("0 12 29 2 *").unwrap();
+        assert_eq!(cron.next_after(time), Some(at(1_709_208_000)));
+
+        // the first of the month or a monday, whichever comes first
+        let cron = Cron::parse("30 9 1 * 1").unwrap();
+        assert_eq!(cron.next_after(time), Some(at(1_709_285_400)));
+        // 7 is sunday as well
+        let cron = Cron::parse("30 9 * * 1,7").unwrap();
+        assert_eq!(cron.next_after(time), Some(at(1_709_458_200)));
+
+        assert_eq!(
+            Cron::parse("@daily").unwrap().next_after(time),
+            Some(at(1_709_164_800))
+        );
+        assert_eq!(Cron::parse("0 0 31 2 *").unwrap().next_after(time), None);
+    }
+
+    #[test]
+    fn invalid() {
+        for expression in &[
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            let error = Cron::parse(expression).unwrap_err();
+            assert!(error.to_string().starts_with("invalid cron expression"));
+        }
+    }
+}
@@ -3,6 +3,13 @@ use super::Station;
 use futures::future::{ready, Ready};
 use futures::prelude::*;
 
+mod cron;
+mod schedule;
+
+pub use self::cron::*;
+pub use self::schedule::*;
+pub use super::clock::*;
+
 pub trait Producer {
     type Item: Send;
     type Future: Future<Output = Option<Result<Self::Item>>> + Send;
@@ -16,7 +23,7 @@ where
     type Item = T::Item;
     type Future = Ready<Option<Result<Self::Item>>>;
     fn next(&mut self) -> Self::Future {
-        match <Iterator<Item = T::Item>>::next(self) {
+        match Iterator::next(self) {
             None => ready(None),
             Some(s) => ready(Some(Ok(s))),
         }
@@ -37,23 +44,23 @@ where
     pub fn new(producer: P, chain: C) -> Consumer<P, C> {
         Consumer {
             stream: producer,
-            chain: chain,
+            chain,
         }
     }
 
-    // pub async fn run(&mut self) -> Vec<Result<C::Output>> {
-    //     let mut out = Vec::new();
-
-    //     loop {
-    //         let next = match await!(self.stream.next()) {
-    //             None => break,
-    //             Some(s) => match s {
-    //                 Ok(m) => await!(self.chain.execute(m)),
-    //                 Err(e) => Err(e),
-    //             },
-    //         };
-    //         out.push(next);
-    //     }
-    //     out
-    // }
+    pub async fn run(&mut self) -> Vec<Result<C::Output>> {
+        let mut out = Vec::new();
+
+        loop {
+            let next = match self.stream.next().await {
+                None => break,
+                Some(s) => match s {
+                    Ok(m) => self.chain.execute(m).await,
+                    Err(e) => Err(e),
+                },
+            };
+            out.push(next);
+        }
+        out
+    }
 }
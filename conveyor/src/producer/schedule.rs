@@ -0,0 +1,303 @@
+use super::super::Result;
+use super::{Clock, Cron, Producer, SystemClock};
+use futures::future::{ready, BoxFuture};
+use futures::prelude::*;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// When a scheduled producer should emit
+pub trait Schedule {
+    /// The first tick after `time`, or None when the schedule has ended
+    fn next_after(&self, time: SystemTime) -> Option<SystemTime>;
+
+    /// The first tick of a producer started at `now`
+    fn first(&self, now: SystemTime) -> Option<SystemTime> {
+        self.next_after(now)
+    }
+}
+
+/// Ticks every `period`, right away or from a fixed start
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    period: Duration,
+    start: Option<SystemTime>,
+}
+
+impl Interval {
+    pub fn new(period: Duration) -> Interval {
+        Interval {
+            period,
+            start: None,
+        }
+    }
+
+    /// Ticks at `start` and every period from there, rather than from when the producer starts
+    pub fn starting_at(mut self, start: SystemTime) -> Interval {
+        self.start = Some(start);
+        self
+    }
+}
+
+impl Schedule for Interval {
+    fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let start = match self.start {
+            Some(start) => start,
+            None => return Some(time + self.period),
+        };
+        let since = match time.duration_since(start) {
+            Ok(since) => since,
+            Err(_) => return Some(start),
+        };
+        if self.period.as_nanos() == 0 {
+            return Some(time);
+        }
+        let ticks = since.as_nanos() / self.period.as_nanos() + 1;
+        Some(start + Duration::from_nanos((self.period.as_nanos() * ticks) as u64))
+    }
+
+    fn first(&self, now: SystemTime) -> Option<SystemTime> {
+        match self.start {
+            Some(start) if start >= now => Some(start),
+            Some(_) => self.next_after(now),
+            None => Some(now),
+        }
+    }
+}
+
+impl Schedule for Box<dyn Schedule + Send> {
+    fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        (**self).next_after(time)
+    }
+
+    fn first(&self, now: SystemTime) -> Option<SystemTime> {
+        (**self).first(now)
+    }
+}
+
+/// What to do with ticks which passed while the producer wasn't polled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MissedTicks {
+    /// Emit once for every missed tick, without waiting
+    Burst,
+    /// Emit once right away, then continue with the next tick in the schedule
+    Skip,
+    /// Emit once right away, shifting the rest of the schedule by how late it was
+    Delay,
+}
+
+/// A producer emitting `make(tick)` on every tick of a schedule, see `every` and `cron`
+pub struct Scheduled<S, F> {
+    schedule: S,
+    make: F,
+    clock: Arc<dyn Clock>,
+    missed: MissedTicks,
+    jitter: Duration,
+    limit: Option<usize>,
+    emitted: usize,
+    /// The next tick, once started
+    due: Option<Option<SystemTime>>,
+    seed: u64,
+}
+
+/// Emits right away and then every `period`
+pub fn every<T, F: FnMut(SystemTime) -> T>(period: Duration, make: F) -> Scheduled<Interval, F> {
+    Scheduled::new(Interval::new(period), make)
+}
+
+/// Emits on the ticks of a cron expression, see `Cron`
+pub fn cron<T, F: FnMut(SystemTime) -> T>(expression: &str, make: F) -> Result<Scheduled<Cron, F>> {
+    Ok(Scheduled::new(Cron::parse(expression)?, make))
+}
+
+impl<S: Schedule, F> Scheduled<S, F> {
+    pub fn new(schedule: S, make: F) -> Scheduled<S, F> {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(0);
+        Scheduled {
+            schedule,
+            make,
+            clock: Arc::new(SystemClock),
+            missed: MissedTicks::Skip,
+            jitter: Duration::default(),
+            limit: None,
+            emitted: 0,
+            due: None,
+            seed: hasher.finish() | 1,
+        }
+    }
+
+    pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Scheduled<S, F> {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Delays every tick by a random duration up to `jitter`, to spread out
+    /// producers sharing a schedule. Late ticks aren't delayed further.
+    pub fn with_jitter(mut self, jitter: Duration) -> Scheduled<S, F> {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Defaults to `MissedTicks::Skip`
+    pub fn on_missed(mut self, missed: MissedTicks) -> Scheduled<S, F> {
+        self.missed = missed;
+        self
+    }
+
+    /// Ends the producer after `limit` ticks
+    pub fn limit(mut self, limit: usize) -> Scheduled<S, F> {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn jitter(&mut self) -> Duration {
+        if self.jitter.as_nanos() == 0 {
+            return Duration::default();
+        }
+        // xorshift
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        let nanos = self.seed as u128 % (self.jitter.as_nanos() + 1);
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+impl<S, T, F> Producer for Scheduled<S, F>
+where
+    S: Schedule,
+    T: Send + 'static,
+    F: FnMut(SystemTime) -> T,
+{
+    type Item = T;
+    type Future = BoxFuture<'static, Option<Result<T>>>;
+
+    fn next(&mut self) -> Self::Future {
+        if self.limit.map(|m| self.emitted >= m).unwrap_or(false) {
+            return ready(None).boxed();
+        }
+        let now = self.clock.now();
+        let tick = match self.due {
+            Some(due) => due,
+            None => self.schedule.first(now),
+        };
+        let tick = match tick {
+            Some(tick) => tick,
+            None => {
+                self.due = Some(None);
+                return ready(None).boxed();
+            }
+        };
+
+        let next = self.schedule.next_after(tick);
+        self.emitted += 1;
+        let item = (self.make)(tick);
+
+        if tick < now {
+            self.due = Some(match self.missed {
+                MissedTicks::Burst => next,
+                MissedTicks::Skip => {
+                    let mut next = next;
+                    while let Some(tick) = next.filter(|m| *m <= now) {
+                        next = self.schedule.next_after(tick);
+                    }
+                    next
+                }
+                MissedTicks::Delay => {
+                    next.map(|next| now + next.duration_since(tick).unwrap_or_default())
+                }
+            });
+            return ready(Some(Ok(item))).boxed();
+        }
+
+        self.due = Some(next);
+        let at = tick + self.jitter();
+        self.clock
+            .sleep_until(at)
+            .map(move |_| Some(Ok(item)))
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+    use futures::executor::block_on;
+    use std::time::UNIX_EPOCH;
+
+    fn secs(time: SystemTime) -> u64 {
+        time.duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    #[test]
+    fn interval() {
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(100));
+        let mut producer = every(Duration::from_secs(10), secs).with_clock(clock.clone());
+
+        assert_eq!(block_on(producer.next()).unwrap().unwrap(), 100);
+        let mut next = producer.next();
+        assert!((&mut next).now_or_never().is_none());
+        clock.advance(Duration::from_secs(9));
+        assert!((&mut next).now_or_never().is_none());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(next.now_or_never().unwrap().unwrap().unwrap(), 110);
+    }
+
+    #[test]
+    fn missed_ticks() {
+        let ticks = |missed: MissedTicks| {
+            let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(100));
+            let mut producer = every(Duration::from_secs(10), secs)
+                .with_clock(clock.clone())
+                .on_missed(missed);
+            block_on(producer.next());
+            // 3.5 ticks pass while nobody polls
+            clock.advance(Duration::from_secs(35));
+            (0..4)
+                .map(|_| {
+                    let next = producer.next();
+                    clock.advance(Duration::from_secs(10));
+                    block_on(next).unwrap().unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ticks(MissedTicks::Burst), vec![110, 120, 130, 140]);
+        assert_eq!(ticks(MissedTicks::Skip), vec![110, 140, 150, 160]);
+        assert_eq!(ticks(MissedTicks::Delay), vec![110, 145, 155, 165]);
+    }
+
+    #[test]
+    fn jitter_and_limit() {
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let start = UNIX_EPOCH + Duration::from_secs(60);
+        let schedule = Interval::new(Duration::from_secs(60)).starting_at(start);
+        let mut producer = Scheduled::new(schedule, secs)
+            .with_clock(clock.clone())
+            .with_jitter(Duration::from_secs(5))
+            .limit(2);
+
+        for tick in &[60, 120] {
+            let mut next = producer.next();
+            clock.advance(Duration::from_secs(*tick - 1 - secs(clock.now())));
+            assert!((&mut next).now_or_never().is_none());
+            clock.advance(Duration::from_secs(6));
+            assert_eq!(block_on(next).unwrap().unwrap(), *tick);
+        }
+        assert!(block_on(producer.next()).is_none());
+    }
+
+    #[test]
+    fn consumer() {
+        let producer = every(Duration::from_millis(1), |_| 2).limit(3);
+        let chain = crate::station_fn(|val: i32| async move { Ok(val * 21) });
+        let ret = block_on(Consumer::new(producer, chain).run());
+        assert_eq!(
+            ret.into_iter().map(|m| m.unwrap()).collect::<Vec<_>>(),
+            vec![42; 3]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use super::{ConveyorError, Result};
+use futures::future::BoxFuture;
+use futures::prelude::*;
+use futures::task::{waker_ref, ArcWake};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll};
+use std::thread;
+
+/// Runs background tasks for stations and producers, so they don't depend
+/// on a specific runtime.
+pub trait Spawner: Send + Sync {
+    fn spawn_boxed(&self, future: BoxFuture<'static, ()>) -> Result<()>;
+
+    /// Runs `task`, which may block, without holding up the spawner's async
+    /// tasks. Runs it on a thread of its own unless the spawner has a pool
+    /// for blocking work.
+    fn spawn_blocking_boxed(&self, task: Box<dyn FnOnce() + Send>) -> Result<()> {
+        thread::Builder::new()
+            .spawn(task)
+            .map(|_| ())
+            .map_err(ConveyorError::new)
+    }
+}
+
+pub trait SpawnerExt: Spawner {
+    fn spawn<F: Future<Output = ()> + Send + 'static>(&self, future: F) -> Result<()> {
+        self.spawn_boxed(Box::pin(future))
+    }
+}
+
+impl<S: Spawner + ?Sized> SpawnerExt for S {}
+
+impl<S: Spawner + ?Sized> Spawner for Arc<S> {
+    fn spawn_boxed(&self, future: BoxFuture<'static, ()>) -> Result<()> {
+        (**self).spawn_boxed(future)
+    }
+
+    fn spawn_blocking_boxed(&self, task: Box<dyn FnOnce() + Send>) -> Result<()> {
+        (**self).spawn_blocking_boxed(task)
+    }
+}
+
+impl<S: Spawner + ?Sized> Spawner for &S {
+    fn spawn_boxed(&self, future: BoxFuture<'static, ()>) -> Result<()> {
+        (**self).spawn_boxed(future)
+    }
+
+    fn spawn_blocking_boxed(&self, task: Box<dyn FnOnce() + Send>) -> Result<()> {
+        (**self).spawn_blocking_boxed(task)
+    }
+}
+
+/// Runs every task to completion on its own OS thread. Needs no runtime.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadSpawner;
+
+impl Spawner for ThreadSpawner {
+    fn spawn_boxed(&self, future: BoxFuture<'static, ()>) -> Result<()> {
+        thread::Builder::new()
+            .spawn(move || futures::executor::block_on(future))
+            .map(|_| ())
+            .map_err(ConveyorError::new)
+    }
+}
+
+#[cfg(feature = "thread-pool")]
+impl Spawner for futures::executor::ThreadPool {
+    fn spawn_boxed(&self, future: BoxFuture<'static, ()>) -> Result<()> {
+        self.spawn_ok(future);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Spawner for tokio::runtime::Handle {
+    fn spawn_boxed(&self, future: BoxFuture<'static, ()>) -> Result<()> {
+        self.spawn(future);
+        Ok(())
+    }
+
+    fn spawn_blocking_boxed(&self, task: Box<dyn FnOnce() + Send>) -> Result<()> {
+        self.spawn_blocking(task);
+        Ok(())
+    }
+}
+
+struct Shared {
+    queue: Mutex<VecDeque<Arc<Task>>>,
+    cond: Condvar,
+}
+
+struct Task {
+    future: Mutex<Option<BoxFuture<'static, ()>>>,
+    queued: AtomicBool,
+    shared: Arc<Shared>,
+}
+
+impl ArcWake for Task {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if !arc_self.queued.swap(true, Ordering::SeqCst) {
+            let mut queue = arc_self.shared.queue.lock().unwrap();
+            queue.push_back(arc_self.clone());
+            arc_self.shared.cond.notify_one();
+        }
+    }
+}
+
+struct MainTask {
+    woken: AtomicBool,
+    shared: Arc<Shared>,
+}
+
+impl ArcWake for MainTask {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        let _queue = arc_self.shared.queue.lock().unwrap();
+        arc_self.woken.store(true, Ordering::SeqCst);
+        arc_self.shared.cond.notify_one();
+    }
+}
+
+/// A single-threaded executor which polls tasks in the order they were
+/// woken, making interleavings reproducible in tests.
+#[derive(Clone)]
+pub struct TestExecutor {
+    shared: Arc<Shared>,
+}
+
+impl TestExecutor {
+    pub fn new() -> TestExecutor {
+        TestExecutor {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(VecDeque::new()),
+                cond: Condvar::new(),
+            }),
+        }
+    }
+
+    fn run_next(&self) -> bool {
+        let task = match self.shared.queue.lock().unwrap().pop_front() {
+            Some(task) => task,
+            None => return false,
+        };
+        task.queued.store(false, Ordering::SeqCst);
+
+        let mut slot = task.future.lock().unwrap();
+        if let Some(mut future) = slot.take() {
+            let waker = waker_ref(&task);
+            let mut cx = Context::from_waker(&waker);
+            if future.as_mut().poll(&mut cx).is_pending() {
+                *slot = Some(future);
+            }
+        }
+        true
+    }
+
+    /// Polls spawned tasks until none of them can make progress,
+    /// returning the number of polls.
+    pub fn run_until_stalled(&self) -> usize {
+        let mut polls = 0;
+        while self.run_next() {
+            polls += 1;
+        }
+        polls
+    }
+
+    /// Drives `future` and the spawned tasks on the current thread until
+    /// `future` resolves.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        futures::pin_mut!(future);
+        let main = Arc::new(MainTask {
+            woken: AtomicBool::new(true),
+            shared: self.shared.clone(),
+        });
+
+        loop {
+            if main.woken.swap(false, Ordering::SeqCst) {
+                let waker = waker_ref(&main);
+                let mut cx = Context::from_waker(&waker);
+                if let Poll::Ready(ret) = future.as_mut().poll(&mut cx) {
+                    return ret;
+                }
+            }
+
+            if self.run_next() {
+                continue;
+            }
+
+            let mut queue = self.shared.queue.lock().unwrap();
+            while queue.is_empty() && !main.woken.load(Ordering::SeqCst) {
+                queue = self.shared.cond.wait(queue).unwrap();
+            }
+        }
+    }
+}
+
+impl Default for TestExecutor {
+    fn default() -> TestExecutor {
+        TestExecutor::new()
+    }
+}
+
+impl Spawner for TestExecutor {
+    fn spawn_boxed(&self, future: BoxFuture<'static, ()>) -> Result<()> {
+        let task = Arc::new(Task {
+            future: Mutex::new(Some(future)),
+            queued: AtomicBool::new(true),
+            shared: self.shared.clone(),
+        });
+        self.shared.queue.lock().unwrap().push_back(task);
+        self.shared.cond.notify_one();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::futures_utils::tests::Pinned;
+    use super::*;
+
+    #[test]
+    fn test_executor_order() {
+        let executor = TestExecutor::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        for i in 0..3 {
+            let log = log.clone();
+            executor
+                .spawn(async move {
+                    log.lock().unwrap().push((i, 0));
+                    Pinned::new(()).await;
+                    log.lock().unwrap().push((i, 1));
+                })
+                .unwrap();
+        }
+
+        assert_eq!(executor.run_until_stalled(), 6);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]
+        );
+    }
+
+    #[test]
+    fn test_executor_block_on() {
+        let executor = TestExecutor::new();
+        let (sx, rx) = futures::channel::oneshot::channel();
+        executor
+            .spawn(async move {
+                Pinned::new(()).await;
+                sx.send(42).unwrap();
+            })
+            .unwrap();
+
+        assert_eq!(executor.block_on(rx).unwrap(), 42);
+    }
+
+    #[test]
+    fn thread_spawner() {
+        let (sx, rx) = futures::channel::oneshot::channel();
+        ThreadSpawner
+            .spawn(async move {
+                sx.send(42).unwrap();
+            })
+            .unwrap();
+
+        assert_eq!(futures::executor::block_on(rx).unwrap(), 42);
+    }
+
+    #[cfg(feature = "thread-pool")]
+    #[test]
+    fn thread_pool() {
+        let pool = futures::executor::ThreadPool::new().unwrap();
+        let (sx, rx) = futures::channel::oneshot::channel();
+        pool.spawn(async move {
+            sx.send(42).unwrap();
+        })
+        .unwrap();
+
+        assert_eq!(futures::executor::block_on(rx).unwrap(), 42);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio_handle() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let (sx, rx) = futures::channel::oneshot::channel();
+        rt.handle()
+            .spawn_boxed(Box::pin(async move {
+                sx.send(42).unwrap();
+            }))
+            .unwrap();
+
+        assert_eq!(rt.block_on(rx).unwrap(), 42);
+    }
+}
@@ -1,9 +1,10 @@
 use super::error::Result;
-use super::futures_utils::Promise;
+use super::futures_utils::{Promise, PromiseProj};
 use futures::Future;
+use pin_project::pin_project;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::task::{Context,Poll};
+use std::task::{Context, Poll};
 
 pub trait Station {
     type Input;
@@ -66,12 +67,14 @@ where
     }
 }
 
+#[pin_project]
 pub struct ConveyorFuture<F, N, O>
 where
     F: Future<Output = Result<O>>,
     N: Station<Input = O>,
 {
     n: Arc<N>,
+    #[pin]
     p: Promise<F, N::Future>,
 }
 
@@ -82,30 +85,18 @@ where
 {
     type Output = Result<N::Output>;
     fn poll(self: Pin<&mut Self>, lw: &mut Context) -> Poll<Self::Output> {
-        let mut this = unsafe { Pin::get_unchecked_mut(self) };
+        let mut this = self.project();
 
         loop {
-            let out = match &mut this.p {
-                Promise::First(fut) => match unsafe { Pin::new_unchecked(fut) }.poll(lw) {
-                    Poll::Pending => Some(Poll::Pending),
-                    Poll::Ready(ret) => {
-                        if ret.is_err() {
-                            Some(Poll::Ready(Err(ret.err().unwrap())))
-                        } else {
-                            this.p = Promise::Second(this.n.execute(ret.unwrap()));
-                            None
-                        }
-                    }
-                },
-                Promise::Second(fut) => match unsafe { Pin::new_unchecked(fut) }.poll(lw) {
-                    Poll::Pending => Some(Poll::Pending),
-                    Poll::Ready(ret) => Some(Poll::Ready(ret)),
+            let next = match this.p.as_mut().project() {
+                PromiseProj::First(fut) => match fut.poll(lw) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Ready(Ok(ret)) => this.n.execute(ret),
                 },
+                PromiseProj::Second(fut) => return fut.poll(lw),
             };
-
-            if out.is_some() {
-                return out.unwrap();
-            }
+            this.p.set(Promise::Second(next));
         }
     }
 }
@@ -170,22 +161,19 @@ where
 {
     StationFnCtx {
         inner: f,
-        ctx: ctx,
+        ctx,
         _i: std::marker::PhantomData,
         _o: std::marker::PhantomData,
     }
 }
 
-pub fn into_box<S: Station + 'static + Send + Sync>(
-    i: S,
-) -> Box<
-    dyn Station<
-            Input = S::Input,
-            Output = S::Output,
-            Future = Pin<Box<dyn Future<Output = Result<S::Output>> + Send>>,
-        > + Send
+pub type BoxStation<I, O> = Box<
+    dyn Station<Input = I, Output = O, Future = Pin<Box<dyn Future<Output = Result<O>> + Send>>>
+        + Send
         + Sync,
-> {
+>;
+
+pub fn into_box<S: Station + 'static + Send + Sync>(i: S) -> BoxStation<S::Input, S::Output> {
     Box::new(Boxed { s: i })
 }
 
@@ -208,13 +196,14 @@ where
 
 #[cfg(test)]
 mod tests {
+    use super::super::futures_utils::tests::Pinned;
     use super::*;
     use futures::executor::block_on;
 
     #[test]
     fn it_works() {
-        let s = station_fn(async move |test: String| Ok(test + ", rapper"));
-        let s = s.pipe(station_fn(async move |test: String| Ok(test + "!")));
+        let s = station_fn(|test: String| async move { Ok(test + ", rapper") });
+        let s = s.pipe(station_fn(|test: String| async move { Ok(test + "!") }));
         let p = s.execute("Hello".to_string());
         let ret = block_on(p).unwrap();
         assert_eq!(ret, "Hello, rapper!");
@@ -222,8 +211,8 @@ mod tests {
 
     #[test]
     fn it_works2() {
-        let s = station_fn(async move |_test: String| Ok(2))
-            .pipe(station_fn(async move |test: i32| Ok(test + 2)));
+        let s = station_fn(|_test: String| async move { Ok(2) })
+            .pipe(station_fn(|test: i32| async move { Ok(test + 2) }));
         let p = s.execute("Hello".to_string());
         let ret = block_on(p).unwrap();
         assert_eq!(ret, 4);
@@ -231,16 +220,31 @@ mod tests {
 
     #[test]
     fn it_works_ctx() {
-        let s = station_fn_ctx(async move |_test: String, ctx: &i32| Ok(2), 10)
-            .pipe(station_fn(async move |test: i32| Ok(test + 2)));
+        let s = station_fn_ctx(|_test: String, _ctx: &i32| async move { Ok(2) }, 10)
+            .pipe(station_fn(|test: i32| async move { Ok(test + 2) }));
         let p = s.execute("Hello".to_string());
         let ret = block_on(p).unwrap();
         assert_eq!(ret, 4);
     }
 
+    #[test]
+    fn not_unpin_stations() {
+        let s = station_fn(|test: String| async move {
+            let suffix = String::from(", rapper");
+            let r = &suffix;
+            Pinned::new(()).await;
+            Ok(test + r)
+        })
+        .pipe(station_fn(|test: String| {
+            Pinned::new(Ok::<_, crate::ConveyorError>(test + "!"))
+        }));
+        let ret = block_on(s.execute("Hello".to_string())).unwrap();
+        assert_eq!(ret, "Hello, rapper!");
+    }
+
     #[test]
     fn boxed() {
-        let s = station_fn(async move |test: &str| Ok(test));
+        let s = station_fn(|test: &'static str| async move { Ok(test) });
         let b = into_box(s);
 
         let ret = futures::executor::block_on(b.execute("Hello")).unwrap();
@@ -0,0 +1,52 @@
+use super::super::{ConveyorError, Result, Station};
+use ::base64::engine::general_purpose::STANDARD;
+use ::base64::Engine;
+use futures::future::{ready, Ready};
+
+#[derive(Clone, Debug)]
+pub struct Base64Encode;
+
+impl Station for Base64Encode {
+    type Input = Vec<u8>;
+    type Output = String;
+    type Future = Ready<Result<String>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(Ok(STANDARD.encode(input)))
+    }
+}
+
+pub fn base64_encode() -> Base64Encode {
+    Base64Encode
+}
+
+#[derive(Clone, Debug)]
+pub struct Base64Decode;
+
+impl Station for Base64Decode {
+    type Input = String;
+    type Output = Vec<u8>;
+    type Future = Ready<Result<Vec<u8>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(STANDARD.decode(input.trim()).map_err(ConveyorError::new))
+    }
+}
+
+pub fn base64_decode() -> Base64Decode {
+    Base64Decode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::Chain;
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn round_trip() {
+        let chain = base64_encode().pipe(base64_decode());
+        let ret = block_on(chain.execute(b"Hello, World!".to_vec())).unwrap();
+        assert_eq!(ret, b"Hello, World!");
+
+        assert!(block_on(base64_decode().execute("!!".to_string())).is_err());
+    }
+}
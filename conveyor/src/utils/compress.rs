@@ -0,0 +1,105 @@
+use super::super::{ConveyorError, Result, Station};
+use flate2::read::{DeflateDecoder, DeflateEncoder, GzDecoder, GzEncoder};
+use flate2::Compression;
+use futures::future::{ready, Ready};
+use std::io::Read;
+
+fn read_all<R: Read>(mut reader: R) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).map_err(ConveyorError::new)?;
+    Ok(buf)
+}
+
+#[derive(Clone, Debug)]
+pub struct GzipCompress {
+    level: Compression,
+}
+
+impl Station for GzipCompress {
+    type Input = Vec<u8>;
+    type Output = Vec<u8>;
+    type Future = Ready<Result<Vec<u8>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(read_all(GzEncoder::new(input.as_slice(), self.level)))
+    }
+}
+
+pub fn gzip_compress() -> GzipCompress {
+    GzipCompress {
+        level: Compression::default(),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GzipDecompress;
+
+impl Station for GzipDecompress {
+    type Input = Vec<u8>;
+    type Output = Vec<u8>;
+    type Future = Ready<Result<Vec<u8>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(read_all(GzDecoder::new(input.as_slice())))
+    }
+}
+
+pub fn gzip_decompress() -> GzipDecompress {
+    GzipDecompress
+}
+
+#[derive(Clone, Debug)]
+pub struct DeflateCompress {
+    level: Compression,
+}
+
+impl Station for DeflateCompress {
+    type Input = Vec<u8>;
+    type Output = Vec<u8>;
+    type Future = Ready<Result<Vec<u8>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(read_all(DeflateEncoder::new(input.as_slice(), self.level)))
+    }
+}
+
+pub fn deflate_compress() -> DeflateCompress {
+    DeflateCompress {
+        level: Compression::default(),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DeflateDecompress;
+
+impl Station for DeflateDecompress {
+    type Input = Vec<u8>;
+    type Output = Vec<u8>;
+    type Future = Ready<Result<Vec<u8>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(read_all(DeflateDecoder::new(input.as_slice())))
+    }
+}
+
+pub fn deflate_decompress() -> DeflateDecompress {
+    DeflateDecompress
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::Chain;
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn round_trip() {
+        let input = b"Hello, World! Hello, World!".to_vec();
+
+        let gzip = block_on(gzip_compress().execute(input.clone())).unwrap();
+        assert_eq!(&gzip[..2], &[0x1f, 0x8b]);
+        let ret = block_on(gzip_decompress().execute(gzip)).unwrap();
+        assert_eq!(ret, input);
+
+        let chain = deflate_compress().pipe(deflate_decompress());
+        assert_eq!(block_on(chain.execute(input.clone())).unwrap(), input);
+
+        assert!(block_on(gzip_decompress().execute(input)).is_err());
+    }
+}
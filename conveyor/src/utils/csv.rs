@@ -0,0 +1,80 @@
+use super::super::{ConveyorError, Result, Station};
+use ::csv::{ReaderBuilder, StringRecord};
+use futures::future::{ready, Ready};
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+
+#[derive(Clone, Debug)]
+pub struct CsvRows;
+
+impl Station for CsvRows {
+    type Input = Vec<u8>;
+    type Output = Vec<Vec<String>>;
+    type Future = Ready<Result<Vec<Vec<String>>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        let ret = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(input.as_slice())
+            .records()
+            .map(|m| {
+                m.map(|r: StringRecord| r.iter().map(|m| m.to_string()).collect())
+                    .map_err(ConveyorError::new)
+            })
+            .collect();
+        ready(ret)
+    }
+}
+
+/// Parses every row, including the first, into a list of fields
+pub fn csv_rows() -> CsvRows {
+    CsvRows
+}
+
+#[derive(Clone, Debug)]
+pub struct CsvRecords<T> {
+    _t: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned + Send> Station for CsvRecords<T> {
+    type Input = Vec<u8>;
+    type Output = Vec<T>;
+    type Future = Ready<Result<Vec<T>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        let ret = ReaderBuilder::new()
+            .from_reader(input.as_slice())
+            .deserialize()
+            .map(|m| m.map_err(ConveyorError::new))
+            .collect();
+        ready(ret)
+    }
+}
+
+/// Deserializes rows into `T`, using the first row as header
+pub fn csv_records<T: DeserializeOwned + Send>() -> CsvRecords<T> {
+    CsvRecords { _t: PhantomData }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+
+    #[test]
+    fn rows() {
+        let ret = block_on(csv_rows().execute(b"a,b\n1,\"2,3\"\n4\n".to_vec())).unwrap();
+        assert_eq!(ret, vec![vec!["a", "b"], vec!["1", "2,3"], vec!["4"]]);
+    }
+
+    #[test]
+    fn records() {
+        let ret =
+            block_on(csv_records::<HashMap<String, u32>>().execute(b"a,b\n1,2\n3,4\n".to_vec()))
+                .unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[1]["b"], 4);
+
+        assert!(block_on(csv_records::<(String, u32)>().execute(b"a,b\nx,y\n".to_vec())).is_err());
+    }
+}
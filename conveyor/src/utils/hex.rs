@@ -0,0 +1,50 @@
+use super::super::{ConveyorError, Result, Station};
+use futures::future::{ready, Ready};
+
+#[derive(Clone, Debug)]
+pub struct HexEncode;
+
+impl Station for HexEncode {
+    type Input = Vec<u8>;
+    type Output = String;
+    type Future = Ready<Result<String>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(Ok(::hex::encode(input)))
+    }
+}
+
+pub fn hex_encode() -> HexEncode {
+    HexEncode
+}
+
+#[derive(Clone, Debug)]
+pub struct HexDecode;
+
+impl Station for HexDecode {
+    type Input = String;
+    type Output = Vec<u8>;
+    type Future = Ready<Result<Vec<u8>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(::hex::decode(input.trim()).map_err(ConveyorError::new))
+    }
+}
+
+pub fn hex_decode() -> HexDecode {
+    HexDecode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn round_trip() {
+        let ret = block_on(hex_encode().execute(vec![0xde, 0xad, 0xbe, 0xef])).unwrap();
+        assert_eq!(ret, "deadbeef");
+        let ret = block_on(hex_decode().execute(ret)).unwrap();
+        assert_eq!(ret, vec![0xde, 0xad, 0xbe, 0xef]);
+
+        assert!(block_on(hex_decode().execute("xyz".to_string())).is_err());
+    }
+}
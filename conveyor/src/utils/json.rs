@@ -0,0 +1,63 @@
+use super::super::{ConveyorError, Result, Station};
+use futures::future::{ready, Ready};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::marker::PhantomData;
+
+#[derive(Clone, Debug)]
+pub struct ToJson<T> {
+    _t: PhantomData<T>,
+}
+
+impl<T: Serialize> Station for ToJson<T> {
+    type Input = T;
+    type Output = Vec<u8>;
+    type Future = Ready<Result<Vec<u8>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(serde_json::to_vec(&input).map_err(ConveyorError::new))
+    }
+}
+
+pub fn to_json<T: Serialize>() -> ToJson<T> {
+    ToJson { _t: PhantomData }
+}
+
+#[derive(Clone, Debug)]
+pub struct FromJson<T> {
+    _t: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned + Send> Station for FromJson<T> {
+    type Input = Vec<u8>;
+    type Output = T;
+    type Future = Ready<Result<T>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(serde_json::from_slice(&input).map_err(ConveyorError::new))
+    }
+}
+
+pub fn from_json<T: DeserializeOwned + Send>() -> FromJson<T> {
+    FromJson { _t: PhantomData }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::Chain;
+    use super::*;
+    use futures::executor::block_on;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn round_trip() {
+        let mut map = BTreeMap::new();
+        map.insert("name".to_string(), 42);
+
+        let ret = block_on(to_json().execute(map.clone())).unwrap();
+        assert_eq!(ret, br#"{"name":42}"#);
+
+        let chain = to_json().pipe(from_json::<BTreeMap<String, i32>>());
+        assert_eq!(block_on(chain.execute(map.clone())).unwrap(), map);
+
+        assert!(block_on(from_json::<BTreeMap<String, i32>>().execute(b"[]".to_vec())).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use super::{Result, Station};
+use futures::future::{ready, Ready};
+
+#[cfg(feature = "base64")]
+mod base64;
+#[cfg(feature = "compress")]
+mod compress;
+#[cfg(feature = "csv")]
+mod csv;
+#[cfg(feature = "hex")]
+mod hex;
+#[cfg(feature = "json")]
+mod json;
+
+#[cfg(feature = "base64")]
+pub use self::base64::*;
+#[cfg(feature = "compress")]
+pub use self::compress::*;
+#[cfg(feature = "csv")]
+pub use self::csv::*;
+#[cfg(feature = "hex")]
+pub use self::hex::*;
+#[cfg(feature = "json")]
+pub use self::json::*;
+
+#[derive(Clone, Debug)]
+pub struct ToString;
+
+impl Station for ToString {
+    type Input = Vec<u8>;
+    type Output = String;
+    type Future = Ready<Result<String>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(String::from_utf8(input).map_err(|e| e.into()))
+    }
+}
+
+pub fn to_string() -> ToString {
+    ToString
+}
+
+#[derive(Clone, Debug)]
+pub struct FromUtf8Lossy;
+
+impl Station for FromUtf8Lossy {
+    type Input = Vec<u8>;
+    type Output = String;
+    type Future = Ready<Result<String>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(Ok(String::from_utf8_lossy(&input).into_owned()))
+    }
+}
+
+pub fn from_utf8_lossy() -> FromUtf8Lossy {
+    FromUtf8Lossy
+}
+
+#[derive(Clone, Debug)]
+pub struct ToBytes<T> {
+    _t: std::marker::PhantomData<T>,
+}
+
+impl<T: Into<Vec<u8>>> Station for ToBytes<T> {
+    type Input = T;
+    type Output = Vec<u8>;
+    type Future = Ready<Result<Vec<u8>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(Ok(input.into()))
+    }
+}
+
+pub fn to_bytes<T: Into<Vec<u8>>>() -> ToBytes<T> {
+    ToBytes {
+        _t: std::marker::PhantomData,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Lines;
+
+impl Station for Lines {
+    type Input = String;
+    type Output = Vec<String>;
+    type Future = Ready<Result<Vec<String>>>;
+    fn execute(&self, input: Self::Input) -> Self::Future {
+        ready(Ok(input.lines().map(|m| m.to_string()).collect()))
+    }
+}
+
+pub fn lines() -> Lines {
+    Lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Chain;
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn text() {
+        let chain = to_bytes().pipe(from_utf8_lossy()).pipe(lines());
+        let ret = block_on(chain.execute("Hello\nWorld\r\n")).unwrap();
+        assert_eq!(ret, vec!["Hello", "World"]);
+
+        let ret = block_on(from_utf8_lossy().execute(vec![b'a', 0xff])).unwrap();
+        assert_eq!(ret, "a\u{fffd}");
+        assert!(block_on(to_string().execute(vec![0xff])).is_err());
+    }
+}
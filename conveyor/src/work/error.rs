@@ -0,0 +1,27 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum WorkError {
+    UnknownStation(String),
+    NotPersistable,
+    /// The run was aborted because of this error, which went to the dead-letter sink
+    Aborted(String),
+    /// A station failed on a remote agent, or the coordinator went away
+    Remote(String),
+}
+
+impl fmt::Display for WorkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorkError::UnknownStation(name) => write!(f, "unknown station: {}", name),
+            WorkError::NotPersistable => {
+                write!(f, "only work with named stations can be persisted")
+            }
+            WorkError::Aborted(error) => write!(f, "aborted: {}", error),
+            WorkError::Remote(error) => write!(f, "remote: {}", error),
+        }
+    }
+}
+
+impl Error for WorkError {}
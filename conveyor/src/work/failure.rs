@@ -0,0 +1,221 @@
+use super::super::ConveyorError;
+use super::Work;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorPolicy {
+    /// Yield the error and carry on with the other work
+    Continue,
+    /// Yield the error and stop, dropping all other work
+    Abort,
+    /// Drop the error along with the queued and running work which
+    /// descends from the same initial work
+    SkipBranch,
+}
+
+/// Work which failed for good, with the last error
+pub struct Failed<V> {
+    pub work: Work<V>,
+    pub error: ConveyorError,
+}
+
+/// Receives work which failed for good, see `Worker::dead_letter`
+pub trait DeadLetter<V>: Send + Sync {
+    fn push(&self, failed: Failed<V>);
+}
+
+/// Keeps failed work in memory. Clones share the same store, so one
+/// can be handed to the worker and the other used to look at the failures.
+pub struct DeadLetterQueue<V> {
+    inner: Arc<Mutex<Vec<Failed<V>>>>,
+}
+
+impl<V> DeadLetterQueue<V> {
+    pub fn new() -> DeadLetterQueue<V> {
+        DeadLetterQueue {
+            inner: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes and returns the failures
+    pub fn take(&self) -> Vec<Failed<V>> {
+        std::mem::take(&mut *self.inner.lock().unwrap())
+    }
+
+    /// Removes the failures and returns their work, ready to be run again
+    pub fn resubmit(&self) -> Vec<Work<V>> {
+        self.take()
+            .into_iter()
+            .map(|failed| {
+                let mut work = failed.work;
+                work.id = None;
+                work.attempts = 0;
+                work
+            })
+            .collect()
+    }
+}
+
+impl<V> Clone for DeadLetterQueue<V> {
+    fn clone(&self) -> Self {
+        DeadLetterQueue {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<V> Default for DeadLetterQueue<V> {
+    fn default() -> Self {
+        DeadLetterQueue::new()
+    }
+}
+
+impl<V: Send> DeadLetter<V> for DeadLetterQueue<V> {
+    fn push(&self, failed: Failed<V>) {
+        self.inner.lock().unwrap().push(failed);
+    }
+}
+
+pub(super) struct Recover<V> {
+    pub(super) clone: fn(&V) -> V,
+    pub(super) retries: usize,
+    pub(super) dead_letter: Option<Arc<dyn DeadLetter<V>>>,
+}
+
+impl<V> Clone for Recover<V> {
+    fn clone(&self) -> Self {
+        Recover {
+            clone: self.clone,
+            retries: self.retries,
+            dead_letter: self.dead_letter.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use super::super::*;
+    use super::*;
+    use futures::executor::block_on;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Fails for values in `failing`, produces two children for values below 10
+    fn tree_worker(failing: &'static [i32], on_error: ErrorPolicy) -> Worker<i32> {
+        let mut worker = Worker::new(WorkerConfig {
+            concurrency: 1,
+            on_error,
+            ..Default::default()
+        });
+        worker.register(
+            "tree",
+            station_fn(move |val: i32| async move {
+                if failing.contains(&val) {
+                    return Err(ConveyorError::new(std::io::Error::other(format!(
+                        "failed {}",
+                        val
+                    ))));
+                }
+                let mut out = vec![WorkOutput::Result(val)];
+                if val < 10 {
+                    out.push(WorkOutput::Work(Work::named(val * 10 + 1, "tree")));
+                    out.push(WorkOutput::Work(Work::named(val * 10 + 2, "tree")));
+                }
+                Ok(out)
+            }),
+        );
+        worker
+    }
+
+    #[test]
+    fn policies() {
+        let worker = tree_worker(&[11], ErrorPolicy::Continue);
+        let ret = block_on(worker.run(vec![Work::named(1, "tree")]));
+        assert_eq!(ret.len(), 3);
+        assert!(ret[1].is_err());
+
+        // 12 is dropped along with 11, the branch of 2 runs to the end
+        let worker = tree_worker(&[11], ErrorPolicy::SkipBranch);
+        let (ret, skipped) =
+            block_on(worker.run_with_skipped(vec![Work::named(1, "tree"), Work::named(2, "tree")]));
+        let mut ret = ret.into_iter().map(|m| m.unwrap()).collect::<Vec<_>>();
+        ret.sort();
+        assert_eq!(ret, vec![1, 2, 21, 22]);
+        assert_eq!(skipped.branch, 1);
+
+        let worker = tree_worker(&[11], ErrorPolicy::Abort);
+        let (ret, skipped) = block_on(worker.run_with_skipped(vec![Work::named(1, "tree")]));
+        assert_eq!(ret.len(), 2);
+        assert!(ret[1].is_err());
+        assert_eq!(skipped.aborted, 1);
+    }
+
+    #[test]
+    fn retry() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut worker = Worker::new(WorkerConfig::default());
+        worker.register(
+            "flaky",
+            station_fn(move |val: i32| {
+                let failed = counter.fetch_add(1, Ordering::SeqCst) < 2;
+                async move {
+                    if failed {
+                        Err(ConveyorError::new(std::io::Error::other("flaky")))
+                    } else {
+                        Ok(vec![WorkOutput::Result(val)])
+                    }
+                }
+            }),
+        );
+
+        worker.retry(1);
+        let ret = block_on(worker.run(vec![Work::named(1, "flaky")]));
+        assert!(ret[0].is_err());
+
+        calls.store(0, Ordering::SeqCst);
+        worker.retry(2);
+        let ret = block_on(worker.run(vec![Work::named(1, "flaky")]));
+        assert_eq!(ret[0].as_ref().unwrap(), &1);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn dead_letter() {
+        let dead_letter = DeadLetterQueue::new();
+        let mut worker = tree_worker(&[11, 12], ErrorPolicy::Continue);
+        worker.retry(1).dead_letter(dead_letter.clone());
+
+        let ret = block_on(worker.run(vec![Work::named(1, "tree")]));
+        assert_eq!(
+            ret.into_iter().map(|m| m.unwrap()).collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert_eq!(dead_letter.len(), 2);
+
+        let failed = dead_letter.take();
+        assert_eq!(failed[0].work.attempts(), 1);
+        assert_eq!(failed[0].error.to_string(), "failed 11");
+        failed
+            .into_iter()
+            .for_each(|failed| dead_letter.push(failed));
+
+        // submit the failed work again to a worker where it succeeds
+        let worker = tree_worker(&[], ErrorPolicy::Continue);
+        let mut ret = block_on(worker.run(dead_letter.resubmit()))
+            .into_iter()
+            .map(|m| m.unwrap())
+            .collect::<Vec<_>>();
+        ret.sort();
+        assert_eq!(ret, vec![11, 12]);
+        assert!(dead_letter.is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+use super::super::{ConveyorError, Result};
+use super::{MemoryQueue, Order, StationRef, Work, WorkError, WorkQueue};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize)]
+struct Entry<D> {
+    id: u64,
+    data: D,
+    stations: Vec<String>,
+    #[serde(default)]
+    key: Option<String>,
+    depth: usize,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    ancestors: Vec<u64>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Record<D> {
+    Push(Entry<D>),
+    Done { id: u64 },
+    Key { key: String },
+}
+
+/// A work queue persisted to an append-only log, so an interrupted run can be resumed
+/// by opening the same file again.
+///
+/// Work that was running when the process stopped is run again on resume.
+pub struct FileQueue<V> {
+    path: PathBuf,
+    file: File,
+    seq: u64,
+    inner: MemoryQueue<V>,
+}
+
+impl<V: Serialize + DeserializeOwned> FileQueue<V> {
+    pub fn open<P: AsRef<Path>>(path: P, order: Order) -> Result<FileQueue<V>> {
+        let path = path.as_ref().to_path_buf();
+
+        let mut pending = BTreeMap::new();
+        let mut keys = BTreeSet::new();
+        let mut seq = 0;
+
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path).map_err(ConveyorError::new)?);
+            let mut lines = reader.lines().peekable();
+            while let Some(line) = lines.next() {
+                let line = line.map_err(ConveyorError::new)?;
+                let record = match serde_json::from_str::<Record<V>>(&line) {
+                    Ok(record) => record,
+                    // a torn write from a crash
+                    Err(_) if lines.peek().is_none() => break,
+                    Err(e) => return Err(ConveyorError::new(e)),
+                };
+                match record {
+                    Record::Push(entry) => {
+                        seq = seq.max(entry.id);
+                        if let Some(key) = &entry.key {
+                            keys.insert(key.clone());
+                        }
+                        pending.insert(entry.id, entry);
+                    }
+                    Record::Done { id } => {
+                        seq = seq.max(id);
+                        pending.remove(&id);
+                    }
+                    Record::Key { key } => {
+                        keys.insert(key);
+                    }
+                }
+            }
+        }
+
+        // compact the log to the keys seen and the work still pending
+        let tmp = path.with_extension("tmp");
+        {
+            let mut file = File::create(&tmp).map_err(ConveyorError::new)?;
+            for key in &keys {
+                write_record(&mut file, &Record::<()>::Key { key: key.clone() })?;
+            }
+            for entry in pending.values() {
+                write_record(&mut file, &Record::Push(entry.as_ref()))?;
+            }
+            file.sync_all().map_err(ConveyorError::new)?;
+        }
+        fs::rename(&tmp, &path).map_err(ConveyorError::new)?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .map_err(ConveyorError::new)?;
+
+        let mut inner = MemoryQueue::new(order);
+        for (_, entry) in pending {
+            inner.push(entry.into_work())?;
+        }
+        for key in keys {
+            inner.mark_seen(key);
+        }
+
+        Ok(FileQueue {
+            path,
+            file,
+            seq,
+            inner,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl<V: Serialize> WorkQueue<V> for FileQueue<V> {
+    fn push(&mut self, mut work: Work<V>) -> Result<bool> {
+        let stations = match work.station_names() {
+            Some(stations) => stations,
+            None => return Err(ConveyorError::new(WorkError::NotPersistable)),
+        };
+        if let Some(key) = &work.key {
+            if self.inner.seen(key) {
+                return Ok(false);
+            }
+        }
+
+        self.seq += 1;
+        work.id = Some(self.seq);
+        write_record(
+            &mut self.file,
+            &Record::Push(Entry {
+                id: self.seq,
+                data: &work.data,
+                stations,
+                key: work.key.clone(),
+                depth: work.depth.unwrap_or(0),
+                priority: work.priority,
+                ancestors: work.ancestors.clone(),
+                tags: work.tags.clone(),
+            }),
+        )?;
+
+        self.inner.push(work)
+    }
+
+    fn pop(&mut self) -> Result<Option<Work<V>>> {
+        self.inner.pop()
+    }
+
+    fn complete(&mut self, id: u64) -> Result<()> {
+        write_record(&mut self.file, &Record::<()>::Done { id })
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl<V> Entry<V> {
+    fn as_ref(&self) -> Entry<&V> {
+        Entry {
+            id: self.id,
+            data: &self.data,
+            stations: self.stations.clone(),
+            key: self.key.clone(),
+            depth: self.depth,
+            priority: self.priority,
+            ancestors: self.ancestors.clone(),
+            tags: self.tags.clone(),
+        }
+    }
+
+    fn into_work(self) -> Work<V> {
+        let mut stations = self.stations.into_iter().map(StationRef::Named);
+        let station = stations
+            .next()
+            .unwrap_or_else(|| StationRef::Named(String::new()));
+        let mut work = Work::with_station(self.data, station);
+        work.next = stations.collect();
+        work.id = Some(self.id);
+        work.key = self.key;
+        work.depth = Some(self.depth);
+        work.priority = self.priority;
+        work.ancestors = self.ancestors;
+        work.tags = self.tags;
+        work
+    }
+}
+
+fn write_record<D: Serialize>(file: &mut File, record: &Record<D>) -> Result<()> {
+    let mut line = serde_json::to_vec(record).map_err(ConveyorError::new)?;
+    line.push(b'\n');
+    file.write_all(&line).map_err(ConveyorError::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use super::super::*;
+    use super::*;
+    use futures::executor::block_on;
+
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "conveyor-queue-{}-{}.log",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn worker() -> Worker<u32> {
+        let mut worker = Worker::new(WorkerConfig {
+            concurrency: 1,
+            ..Default::default()
+        });
+        worker.register(
+            "tree",
+            station_fn(|val: u32| async move {
+                let mut out = vec![WorkOutput::Result(val)];
+                if val < 100 {
+                    out.push(WorkOutput::Work(Work::named(val * 10 + 1, "tree")));
+                    out.push(WorkOutput::Work(Work::named(val * 10 + 2, "tree")));
+                }
+                Ok(out)
+            }),
+        );
+        worker
+    }
+
+    #[test]
+    fn resume() {
+        let path = temp_file("resume");
+        let worker = worker();
+
+        let queue = FileQueue::open(&path, Order::BreadthFirst).unwrap();
+        let stream = worker.stream_with(queue, vec![Work::named(1, "tree").with_key("root")]);
+        // stop half-way through, as if the process died
+        let first = block_on(stream.take(3).map(|m| m.unwrap()).collect::<Vec<_>>());
+        assert_eq!(first, vec![1, 11, 12]);
+
+        let queue = FileQueue::open(&path, Order::BreadthFirst).unwrap();
+        assert_eq!(queue.len(), 4);
+        let stream = worker.stream_with(queue, vec![Work::named(1, "tree").with_key("root")]);
+        let rest = block_on(stream.map(|m| m.unwrap()).collect::<Vec<_>>());
+        assert_eq!(rest, vec![111, 112, 121, 122]);
+
+        let queue = FileQueue::<u32>::open(&path, Order::BreadthFirst).unwrap();
+        assert!(queue.is_empty());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unnamed_work() {
+        let path = temp_file("unnamed");
+        let mut queue = FileQueue::open(&path, Order::BreadthFirst).unwrap();
+        let work = Work::new(
+            1,
+            station_fn(|val: u32| async move { Ok(vec![WorkOutput::Result(val)]) }),
+        );
+        assert!(queue.push(work).is_err());
+        assert!(queue
+            .push(Work::named(1, "tree").chain_named("log"))
+            .unwrap());
+
+        fs::remove_file(path).unwrap();
+    }
+}
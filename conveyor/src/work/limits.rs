@@ -0,0 +1,60 @@
+use super::super::Clock;
+use futures::future::BoxFuture;
+use futures::prelude::*;
+use std::task::Context;
+use std::time::Duration;
+
+/// Work a worker didn't run because a limit was hit or the run was aborted
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Skipped {
+    /// Left in the queue after `max_items` was reached
+    pub items: usize,
+    /// Child work deeper than `max_depth`
+    pub depth: usize,
+    /// Running or queued when the `time_budget` ran out
+    pub time: usize,
+    /// Child work beyond `max_fan_out` of its key group
+    pub fan_out: usize,
+    /// Running or queued when an error aborted the run
+    pub aborted: usize,
+    /// Running or queued in the branch of failed work, see `ErrorPolicy::SkipBranch`
+    pub branch: usize,
+}
+
+impl Skipped {
+    pub fn total(&self) -> usize {
+        self.items + self.depth + self.time + self.fan_out + self.aborted + self.branch
+    }
+}
+
+/// The host of a url key like `https://host/path`, or else the whole key.
+/// The default `WorkerConfig::key_group`.
+pub fn url_host(key: &str) -> &str {
+    match key.find("://") {
+        Some(idx) => key[idx + 3..].split(['/', '?', '#']).next().unwrap_or(""),
+        None => key,
+    }
+}
+
+/// Resolves once the worker's time budget is spent, even when all
+/// running work is stuck.
+pub(super) struct Deadline {
+    sleep: BoxFuture<'static, ()>,
+    expired: bool,
+}
+
+impl Deadline {
+    pub(super) fn new(clock: &dyn Clock, budget: Duration) -> Deadline {
+        Deadline {
+            sleep: clock.sleep_until(clock.now() + budget),
+            expired: false,
+        }
+    }
+
+    pub(super) fn expired(&mut self, cx: &mut Context) -> bool {
+        if !self.expired {
+            self.expired = self.sleep.poll_unpin(cx).is_ready();
+        }
+        self.expired
+    }
+}
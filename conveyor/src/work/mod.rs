@@ -0,0 +1,948 @@
+use super::{into_box, Clock, ConveyorError, Result, Station, SystemClock};
+use futures::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod error;
+mod failure;
+#[cfg(feature = "persist")]
+mod file;
+mod limits;
+mod queue;
+#[cfg(feature = "remote")]
+mod remote;
+mod report;
+mod stream;
+
+pub use self::error::*;
+pub use self::failure::{DeadLetter, DeadLetterQueue, ErrorPolicy, Failed};
+#[cfg(feature = "persist")]
+pub use self::file::*;
+pub use self::limits::{url_host, Skipped};
+pub use self::queue::*;
+#[cfg(feature = "remote")]
+pub use self::remote::*;
+pub use self::report::*;
+pub use self::stream::*;
+
+use self::failure::Recover;
+
+pub enum WorkOutput<V> {
+    Result(V),
+    Work(Work<V>),
+}
+
+pub fn run<V: 'static>(input: Vec<Work<V>>) -> impl Future<Output = Vec<Result<V>>> {
+    Worker::default().run(input)
+}
+
+pub type WorkBox<V> = Box<
+    dyn Station<
+            Input = V,
+            Output = Vec<WorkOutput<V>>,
+            Future = Pin<Box<dyn Future<Output = Result<Vec<WorkOutput<V>>>> + Send>>,
+        > + Send
+        + Sync,
+>;
+
+type Stations<V> = HashMap<String, Arc<WorkBox<V>>>;
+
+enum StationRef<V> {
+    Named(String),
+    Boxed(Arc<WorkBox<V>>),
+}
+
+impl<V> Clone for StationRef<V> {
+    fn clone(&self) -> Self {
+        match self {
+            StationRef::Named(name) => StationRef::Named(name.clone()),
+            StationRef::Boxed(station) => StationRef::Boxed(station.clone()),
+        }
+    }
+}
+
+impl<V> StationRef<V> {
+    fn boxed<
+        W: Station<Input = V, Output = Vec<WorkOutput<V>>, Future = F> + 'static + Send + Sync,
+        F: Future<Output = Result<Vec<WorkOutput<V>>>> + Send,
+    >(
+        work: W,
+    ) -> StationRef<V> {
+        StationRef::Boxed(Arc::new(into_box(work)))
+    }
+
+    fn resolve(&self, stations: &Stations<V>) -> Result<Arc<WorkBox<V>>> {
+        match self {
+            StationRef::Boxed(station) => Ok(station.clone()),
+            StationRef::Named(name) => stations
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ConveyorError::new(WorkError::UnknownStation(name.clone()))),
+        }
+    }
+}
+
+pub struct Work<V> {
+    data: V,
+    station: StationRef<V>,
+    next: Vec<StationRef<V>>,
+    id: Option<u64>,
+    key: Option<String>,
+    depth: Option<usize>,
+    priority: i32,
+    ancestors: Vec<u64>,
+    tags: Vec<String>,
+    attempts: usize,
+}
+
+/// Where a result came from
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Lineage {
+    /// The id of the work which produced the result
+    pub id: Option<u64>,
+    /// The ids of the work leading up to it, starting at the initial work
+    pub ancestors: Vec<u64>,
+    pub depth: usize,
+    pub tags: Vec<String>,
+}
+
+impl Lineage {
+    pub fn parent(&self) -> Option<u64> {
+        self.ancestors.last().cloned()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkResult<V> {
+    pub value: V,
+    pub lineage: Lineage,
+}
+
+impl<V> Work<V> {
+    pub fn new<
+        W: Station<Input = V, Output = Vec<WorkOutput<V>>, Future = F> + 'static + Send + Sync,
+        F: Future<Output = Result<Vec<WorkOutput<V>>>> + Send,
+    >(
+        data: V,
+        work: W,
+    ) -> Work<V> {
+        Work::with_station(data, StationRef::boxed(work))
+    }
+
+    /// Work running a station registered on the worker with `Worker::register`.
+    /// Only work made of named stations can be persisted by a queue.
+    pub fn named<S: Into<String>>(data: V, name: S) -> Work<V> {
+        Work::with_station(data, StationRef::Named(name.into()))
+    }
+
+    fn with_station(data: V, station: StationRef<V>) -> Work<V> {
+        Work {
+            data,
+            station,
+            next: Vec::new(),
+            id: None,
+            key: None,
+            depth: None,
+            priority: 0,
+            ancestors: Vec::new(),
+            tags: Vec::new(),
+            attempts: 0,
+        }
+    }
+
+    /// Work with a higher priority runs first with `Order::Priority`,
+    /// and before other work at the same depth otherwise
+    pub fn with_priority(mut self, priority: i32) -> Work<V> {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_tag<S: Into<String>>(mut self, tag: S) -> Work<V> {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// The id assigned when the work was queued
+    pub fn id(&self) -> Option<u64> {
+        self.id
+    }
+
+    /// The id of the work which produced this work
+    pub fn parent(&self) -> Option<u64> {
+        self.ancestors.last().cloned()
+    }
+
+    /// How many steps away from the initial work this is, once queued
+    pub fn depth(&self) -> usize {
+        self.depth.unwrap_or(0)
+    }
+
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// How many times this work failed and was retried
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    pub fn lineage(&self) -> Lineage {
+        Lineage {
+            id: self.id,
+            ancestors: self.ancestors.clone(),
+            depth: self.depth(),
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Work with a key is only queued once per key, also across restarts
+    /// when the queue is persistent.
+    pub fn with_key<S: Into<String>>(mut self, key: S) -> Work<V> {
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Feeds every result of this work into `next`.
+    /// Child work produced along the way continues with the rest of the chain.
+    pub fn chain<
+        W: Station<Input = V, Output = Vec<WorkOutput<V>>, Future = F> + 'static + Send + Sync,
+        F: Future<Output = Result<Vec<WorkOutput<V>>>> + Send,
+    >(
+        mut self,
+        next: W,
+    ) -> Work<V> {
+        self.next.push(StationRef::boxed(next));
+        self
+    }
+
+    /// Like `chain`, with a station registered on the worker
+    pub fn chain_named<S: Into<String>>(mut self, name: S) -> Work<V> {
+        self.next.push(StationRef::Named(name.into()));
+        self
+    }
+
+    /// The names of this work's stations, or None when one of them isn't named
+    #[cfg(feature = "persist")]
+    fn station_names(&self) -> Option<Vec<String>> {
+        std::iter::once(&self.station)
+            .chain(self.next.iter())
+            .map(|m| match m {
+                StationRef::Named(name) => Some(name.clone()),
+                StationRef::Boxed(_) => None,
+            })
+            .collect()
+    }
+
+    fn station_name(&self) -> &str {
+        match &self.station {
+            StationRef::Named(name) => name,
+            StationRef::Boxed(_) => "<unnamed>",
+        }
+    }
+
+    fn duplicate(&self, clone: fn(&V) -> V) -> Work<V> {
+        let mut work = Work::with_station(clone(&self.data), self.station.clone());
+        work.next = self.next.clone();
+        work.id = self.id;
+        work.key = self.key.clone();
+        work.depth = self.depth;
+        work.priority = self.priority;
+        work.ancestors = self.ancestors.clone();
+        work.tags = self.tags.clone();
+        work.attempts = self.attempts;
+        work
+    }
+
+    fn execute(
+        self,
+        stations: &Stations<V>,
+    ) -> Result<impl Future<Output = Result<Vec<WorkOutput<V>>>>> {
+        let station = self.station.resolve(stations)?;
+        let next = self.next;
+        let depth = self.depth;
+        let priority = self.priority;
+        let tags = self.tags;
+        let mut ancestors = self.ancestors;
+        ancestors.extend(self.id);
+        Ok(station.execute(self.data).map_ok(move |outputs| {
+            if next.is_empty() {
+                return outputs;
+            }
+            outputs
+                .into_iter()
+                .map(|output| match output {
+                    WorkOutput::Result(data) => {
+                        let mut work = Work::with_station(data, next[0].clone());
+                        work.next = next[1..].to_vec();
+                        // the rest of the chain is the same step, not a child
+                        work.depth = depth;
+                        work.priority = priority;
+                        work.ancestors = ancestors.clone();
+                        work.tags = tags.clone();
+                        WorkOutput::Work(work)
+                    }
+                    WorkOutput::Work(mut work) => {
+                        work.next.extend(next.iter().cloned());
+                        WorkOutput::Work(work)
+                    }
+                })
+                .collect()
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    /// Run shallow work before deeper work
+    BreadthFirst,
+    /// Follow the most recently produced child work first
+    DepthFirst,
+    /// Run work with the highest priority first
+    Priority,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WorkerConfig {
+    /// Maximum number of work items running at once. 0 means unbounded
+    pub concurrency: usize,
+    /// Child work deeper than this is dropped. The initial work has depth 0
+    pub max_depth: Option<usize>,
+    pub order: Order,
+    /// Stop starting work after this many items. The rest stays in the queue
+    pub max_items: Option<usize>,
+    /// Stop after this long, dropping the work still running
+    pub time_budget: Option<Duration>,
+    /// Child work queued per key group, the rest is dropped.
+    /// Bounds e.g. the pages crawled per host. Work without a key isn't limited
+    pub max_fan_out: Option<usize>,
+    /// Groups keys for `max_fan_out`, by host for url keys by default
+    pub key_group: fn(&str) -> &str,
+    /// What to do with work which failed for good
+    pub on_error: ErrorPolicy,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> WorkerConfig {
+        WorkerConfig {
+            concurrency: 4,
+            max_depth: None,
+            order: Order::BreadthFirst,
+            max_items: None,
+            time_budget: None,
+            max_fan_out: None,
+            key_group: url_host,
+            on_error: ErrorPolicy::Continue,
+        }
+    }
+}
+
+pub struct Worker<V> {
+    config: WorkerConfig,
+    stations: Stations<V>,
+    recover: Option<Recover<V>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<V> Default for Worker<V> {
+    fn default() -> Worker<V> {
+        Worker::new(WorkerConfig::default())
+    }
+}
+
+impl<V> Worker<V> {
+    pub fn new(config: WorkerConfig) -> Worker<V> {
+        Worker {
+            config,
+            stations: HashMap::new(),
+            recover: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn config(&self) -> &WorkerConfig {
+        &self.config
+    }
+
+    /// The clock measuring the `time_budget`, the system's by default
+    pub fn with_clock<C: Clock + 'static>(&mut self, clock: C) -> &mut Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Registers a station which work can refer to by name, see `Work::named`
+    pub fn register<
+        S: AsRef<str>,
+        W: Station<Input = V, Output = Vec<WorkOutput<V>>, Future = F> + 'static + Send + Sync,
+        F: Future<Output = Result<Vec<WorkOutput<V>>>> + Send,
+    >(
+        &mut self,
+        name: S,
+        station: W,
+    ) -> &mut Self {
+        self.stations
+            .insert(name.as_ref().to_string(), Arc::new(into_box(station)));
+        self
+    }
+}
+
+impl<V: Clone + 'static> Worker<V> {
+    /// Runs failed work again, up to `times` times, before giving up on it
+    pub fn retry(&mut self, times: usize) -> &mut Self {
+        self.recover().retries = times;
+        self
+    }
+
+    /// Hands work which failed for good to `sink` instead of yielding the error,
+    /// so it can be inspected and submitted again later
+    pub fn dead_letter<D: DeadLetter<V> + 'static>(&mut self, sink: D) -> &mut Self {
+        self.recover().dead_letter = Some(Arc::new(sink));
+        self
+    }
+
+    fn recover(&mut self) -> &mut Recover<V> {
+        self.recover.get_or_insert_with(|| Recover {
+            clone: V::clone,
+            retries: 0,
+            dead_letter: None,
+        })
+    }
+}
+
+impl<V: 'static> Worker<V> {
+    /// Runs `input` and all the work it produces, starting child work
+    /// as soon as there is room for it.
+    pub fn run(&self, input: Vec<Work<V>>) -> impl Future<Output = Vec<Result<V>>> {
+        self.stream(input).collect()
+    }
+
+    /// Like `run`, also returning what was left out because of the limits
+    pub fn run_with_skipped(
+        &self,
+        input: Vec<Work<V>>,
+    ) -> impl Future<Output = (Vec<Result<V>>, Skipped)> {
+        let stream = self.stream(input);
+        async move {
+            futures::pin_mut!(stream);
+            let mut output = Vec::new();
+            while let Some(next) = stream.next().await {
+                output.push(next);
+            }
+            (output, *stream.skipped())
+        }
+    }
+
+    /// Like `run`, also returning statistics of the run.
+    /// Use `stream(..).report()` to follow a run while it's going.
+    pub fn run_with_report(
+        &self,
+        input: Vec<Work<V>>,
+    ) -> impl Future<Output = (Vec<Result<V>>, Report)> {
+        let stream = self.stream(input);
+        let report = stream.report();
+        stream
+            .collect()
+            .map(move |output| (output, report.snapshot()))
+    }
+
+    /// Like `run`, with the lineage of every result
+    pub fn run_traced(
+        &self,
+        input: Vec<Work<V>>,
+    ) -> impl Future<Output = Vec<Result<WorkResult<V>>>> {
+        self.stream(input).traced().collect()
+    }
+
+    /// Like `run`, but yields results as soon as they are produced.
+    /// No new work is started while results are waiting to be consumed.
+    pub fn stream(&self, input: Vec<Work<V>>) -> WorkStream<V> {
+        self.stream_with(MemoryQueue::new(self.config.order), input)
+    }
+
+    /// Like `stream`, keeping pending work in `queue`.
+    /// Work already in the queue, e.g. from an earlier interrupted run, is resumed
+    /// before `input`, and input with a key the queue has seen is skipped.
+    pub fn stream_with<Q: WorkQueue<V>>(
+        &self,
+        mut queue: Q,
+        input: Vec<Work<V>>,
+    ) -> WorkStream<V, Q> {
+        let mut output = VecDeque::new();
+        for mut work in input {
+            work.depth = Some(0);
+            if let Err(e) = queue.push(work) {
+                output.push_back(Err(e));
+            }
+        }
+
+        WorkStream::new(
+            self.config,
+            self.stations.clone(),
+            self.recover.clone(),
+            self.clock.clone(),
+            queue,
+            output,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::*;
+    use super::*;
+    use futures;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    #[test]
+    fn it_works() {
+        let work = Work::new(
+            String::from("Value, baby!"),
+            station_fn(|val| async move { Ok(vec![WorkOutput::Result(val)]) }),
+        );
+
+        let worker = Worker::default();
+
+        let ret = futures::executor::block_on(worker.run(vec![work]));
+
+        assert_eq!(ret.len(), 1);
+        assert!(ret[0].is_ok());
+        //assert_eq!(&ret[0].unwrap(), String::from("Value, baby!"));
+    }
+
+    #[test]
+    fn more_work() {
+        let work = Work::new(
+            String::from("Value, baby!"),
+            station_fn(|val: String| async move {
+                Ok(vec![
+                    WorkOutput::Result(val),
+                    WorkOutput::Work(Work::new(
+                        String::from("Value, baby 2!"),
+                        station_fn(|val| async move { Ok(vec![WorkOutput::Result(val)]) }),
+                    )),
+                ])
+            }),
+        );
+
+        let worker = Worker::default();
+
+        let ret = futures::executor::block_on(worker.run(vec![work]));
+
+        assert_eq!(ret.len(), 2);
+        //assert_eq!(&ret[0].unwrap(), String::from("Value, baby!"));
+    }
+
+    #[test]
+    fn chain() {
+        let work = Work::new(
+            1,
+            station_fn(|val: i32| async move {
+                Ok(vec![
+                    WorkOutput::Result(val + 1),
+                    WorkOutput::Result(val + 2),
+                ])
+            }),
+        )
+        .chain(station_fn(|val: i32| async move {
+            Ok(vec![WorkOutput::Result(val * 10)])
+        }))
+        .chain(station_fn(|val: i32| async move {
+            Ok(vec![WorkOutput::Result(val + 5)])
+        }));
+
+        let ret = futures::executor::block_on(Worker::default().run(vec![work]));
+        let mut ret = ret.into_iter().map(|m| m.unwrap()).collect::<Vec<_>>();
+        ret.sort();
+        assert_eq!(ret, vec![25, 35]);
+    }
+
+    #[test]
+    fn chain_child_work() {
+        let work = Work::new(
+            1,
+            station_fn(|val: i32| async move {
+                Ok(vec![
+                    WorkOutput::Result(val),
+                    WorkOutput::Work(
+                        Work::new(
+                            val + 1,
+                            station_fn(|val: i32| async move {
+                                Ok(vec![WorkOutput::Result(val * 100)])
+                            }),
+                        )
+                        .chain(station_fn(|val: i32| async move {
+                            Ok(vec![WorkOutput::Result(val + 1)])
+                        })),
+                    ),
+                ])
+            }),
+        )
+        .chain(station_fn(|val: i32| async move {
+            Ok(vec![WorkOutput::Result(-val)])
+        }));
+
+        let ret = futures::executor::block_on(Worker::default().run(vec![work]));
+        let mut ret = ret.into_iter().map(|m| m.unwrap()).collect::<Vec<_>>();
+        ret.sort();
+        // the child runs its own chain first, then the remainder of the parent's
+        assert_eq!(ret, vec![-201, -1]);
+    }
+
+    fn tree(val: i32, depth: i32) -> Work<i32> {
+        Work::new(
+            val,
+            station_fn(move |val: i32| async move {
+                let mut out = vec![WorkOutput::Result(val)];
+                if depth > 0 {
+                    out.push(WorkOutput::Work(tree(val * 10 + 1, depth - 1)));
+                    out.push(WorkOutput::Work(tree(val * 10 + 2, depth - 1)));
+                }
+                Ok(out)
+            }),
+        )
+    }
+
+    fn run_with(config: WorkerConfig, input: Vec<Work<i32>>) -> Vec<i32> {
+        let ret = futures::executor::block_on(Worker::new(config).run(input));
+        ret.into_iter().map(|m| m.unwrap()).collect()
+    }
+
+    #[test]
+    fn order() {
+        let config = WorkerConfig {
+            concurrency: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            run_with(config, vec![tree(1, 2)]),
+            vec![1, 11, 12, 111, 112, 121, 122]
+        );
+
+        let config = WorkerConfig {
+            concurrency: 1,
+            order: Order::DepthFirst,
+            ..Default::default()
+        };
+        assert_eq!(
+            run_with(config, vec![tree(1, 2)]),
+            vec![1, 12, 122, 121, 11, 112, 111]
+        );
+
+        let config = WorkerConfig {
+            concurrency: 1,
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(run_with(config, vec![tree(1, 2)]), vec![1, 11, 12]);
+    }
+
+    #[test]
+    fn priority() {
+        let config = WorkerConfig {
+            concurrency: 1,
+            order: Order::Priority,
+            ..Default::default()
+        };
+        let input = vec![
+            tree(1, 0).with_priority(1),
+            tree(2, 0).with_priority(3),
+            tree(3, 0),
+            tree(4, 0).with_priority(3),
+        ];
+        assert_eq!(run_with(config, input), vec![2, 4, 1, 3]);
+    }
+
+    #[test]
+    fn children_do_not_wait_for_siblings() {
+        // the first item only finishes once a child of the second one ran,
+        // which never happens when work is processed in generations
+        let (sx, rx) = futures::channel::oneshot::channel::<i32>();
+        let sx = Arc::new(std::sync::Mutex::new(Some(sx)));
+        let rx = Arc::new(std::sync::Mutex::new(Some(rx)));
+
+        let slow = Work::new(
+            1,
+            station_fn(move |_: i32| {
+                let rx = rx.lock().unwrap().take().unwrap();
+                async move { Ok(vec![WorkOutput::Result(rx.await.unwrap())]) }
+            }),
+        );
+        let fast = Work::new(
+            2,
+            station_fn(move |val: i32| {
+                let sx = sx.clone();
+                async move {
+                    Ok(vec![WorkOutput::Work(Work::new(
+                        val,
+                        station_fn(move |val: i32| {
+                            sx.lock().unwrap().take().unwrap().send(val * 10).unwrap();
+                            futures::future::ready(Ok(vec![WorkOutput::Result(val)]))
+                        }),
+                    ))])
+                }
+            }),
+        );
+
+        let config = WorkerConfig {
+            concurrency: 2,
+            ..Default::default()
+        };
+        let mut ret = run_with(config, vec![slow, fast]);
+        ret.sort();
+        assert_eq!(ret, vec![2, 20]);
+    }
+
+    fn counting(val: i32, started: Arc<AtomicUsize>) -> Work<i32> {
+        Work::new(
+            val,
+            station_fn(move |val: i32| {
+                started.fetch_add(1, AtomicOrdering::SeqCst);
+                let mut out = vec![WorkOutput::Result(val)];
+                if val < 10 {
+                    out.push(WorkOutput::Work(counting(val + 1, started.clone())));
+                }
+                futures::future::ready(Ok(out))
+            }),
+        )
+    }
+
+    #[test]
+    fn stream_backpressure() {
+        let started = Arc::new(AtomicUsize::new(0));
+        let worker = Worker::new(WorkerConfig {
+            concurrency: 1,
+            ..Default::default()
+        });
+        let mut stream = worker.stream(vec![counting(0, started.clone())]);
+
+        futures::executor::block_on(async {
+            assert_eq!(stream.next().await.unwrap().unwrap(), 0);
+            assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+            // nothing runs ahead of the consumer
+            assert_eq!(started.load(AtomicOrdering::SeqCst), 2);
+
+            let rest = stream.map(|m| m.unwrap()).collect::<Vec<_>>().await;
+            assert_eq!(rest, (2..=10).collect::<Vec<_>>());
+        });
+        assert_eq!(started.load(AtomicOrdering::SeqCst), 11);
+    }
+
+    #[test]
+    fn lineage() {
+        let work = Work::new(
+            1,
+            station_fn(|val: i32| async move {
+                Ok(vec![
+                    WorkOutput::Result(val),
+                    WorkOutput::Work(tree(val * 10 + 1, 0).with_tag("deep")),
+                    WorkOutput::Work(tree(val * 10 + 2, 0).with_tag("sitemap").with_priority(1)),
+                ])
+            }),
+        )
+        .with_tag("root");
+
+        let worker = Worker::new(WorkerConfig {
+            concurrency: 1,
+            ..Default::default()
+        });
+        let ret = futures::executor::block_on(worker.run_traced(vec![work]))
+            .into_iter()
+            .map(|m| m.unwrap())
+            .collect::<Vec<_>>();
+
+        // the sitemap child has a higher priority than its sibling at the same depth
+        assert_eq!(
+            ret.iter().map(|m| m.value).collect::<Vec<_>>(),
+            vec![1, 12, 11]
+        );
+        let root = ret[0].lineage.id.unwrap();
+        assert_eq!(ret[0].lineage.tags, vec!["root"]);
+        assert_eq!(ret[0].lineage.parent(), None);
+        assert_eq!(ret[1].lineage.parent(), Some(root));
+        assert_eq!(ret[1].lineage.depth, 1);
+        assert_eq!(ret[1].lineage.tags, vec!["sitemap"]);
+        assert_eq!(ret[2].lineage.ancestors, vec![root]);
+    }
+
+    #[test]
+    fn limits() {
+        let worker = Worker::new(WorkerConfig {
+            concurrency: 1,
+            max_items: Some(3),
+            ..Default::default()
+        });
+        let (ret, skipped) = futures::executor::block_on(worker.run_with_skipped(vec![tree(1, 2)]));
+        assert_eq!(ret.len(), 3);
+        assert_eq!(
+            skipped,
+            Skipped {
+                items: 4,
+                ..Default::default()
+            }
+        );
+
+        let worker = Worker::new(WorkerConfig {
+            max_depth: Some(1),
+            ..Default::default()
+        });
+        let (ret, skipped) = futures::executor::block_on(worker.run_with_skipped(vec![tree(1, 2)]));
+        let mut ret = ret.into_iter().map(|m| m.unwrap()).collect::<Vec<_>>();
+        ret.sort();
+        assert_eq!(ret, vec![1, 11, 12]);
+        assert_eq!(skipped.depth, 4);
+        assert_eq!(skipped.total(), 4);
+    }
+
+    #[test]
+    fn fan_out() {
+        assert_eq!(url_host("https://a.com:8080/b?c"), "a.com:8080");
+        assert_eq!(url_host("http://a.com"), "a.com");
+        assert_eq!(url_host("src/lib.rs"), "src/lib.rs");
+
+        // the first page links to three pages on each of two hosts
+        let mut worker = Worker::new(WorkerConfig {
+            max_fan_out: Some(2),
+            ..Default::default()
+        });
+        worker.register(
+            "crawl",
+            station_fn(|url: String| async move {
+                let mut out = vec![];
+                if url.ends_with(".com/") {
+                    for n in 1..=3 {
+                        for host in &["a", "b"] {
+                            let link = format!("http://{}.com/{}", host, n);
+                            out.push(WorkOutput::Work(
+                                Work::named(link.clone(), "crawl").with_key(link),
+                            ));
+                        }
+                    }
+                }
+                out.push(WorkOutput::Result(url));
+                Ok(out)
+            }),
+        );
+        let root = "http://a.com/".to_string();
+        let work = Work::named(root.clone(), "crawl").with_key(root);
+        let (ret, skipped) = futures::executor::block_on(worker.run_with_skipped(vec![work]));
+        let mut ret = ret.into_iter().map(|m| m.unwrap()).collect::<Vec<_>>();
+        ret.sort();
+        assert_eq!(
+            ret,
+            vec![
+                "http://a.com/",
+                "http://a.com/1",
+                "http://a.com/2",
+                "http://b.com/1",
+                "http://b.com/2"
+            ]
+        );
+        assert_eq!(skipped.fan_out, 2);
+    }
+
+    #[test]
+    fn time_budget() {
+        let worker = Worker::new(WorkerConfig {
+            concurrency: 1,
+            time_budget: Some(std::time::Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let stuck = || {
+            Work::new(
+                0,
+                station_fn(|_: i32| future::pending::<Result<Vec<WorkOutput<i32>>>>()),
+            )
+        };
+        let (ret, skipped) = futures::executor::block_on(worker.run_with_skipped(vec![
+            tree(1, 0),
+            stuck(),
+            stuck(),
+        ]));
+        assert_eq!(
+            ret.into_iter().map(|m| m.unwrap()).collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert_eq!(skipped.time, 2);
+    }
+
+    #[test]
+    fn time_budget_clock() {
+        let clock = ManualClock::new(std::time::UNIX_EPOCH);
+        let mut worker = Worker::new(WorkerConfig {
+            time_budget: Some(std::time::Duration::from_secs(60)),
+            ..Default::default()
+        });
+        worker.with_clock(clock.clone());
+        let stuck = Work::new(
+            0,
+            station_fn(|_: i32| future::pending::<Result<Vec<WorkOutput<i32>>>>()),
+        );
+
+        let mut stream = worker.stream(vec![stuck]);
+        let poll = |stream: &mut WorkStream<i32>| {
+            futures::executor::block_on(future::poll_fn(|cx| {
+                std::task::Poll::Ready(stream.poll_next_unpin(cx).is_pending())
+            }))
+        };
+        assert!(poll(&mut stream));
+        clock.advance(std::time::Duration::from_secs(59));
+        assert!(poll(&mut stream));
+        clock.advance(std::time::Duration::from_secs(1));
+        assert!(futures::executor::block_on(stream.next()).is_none());
+    }
+
+    #[test]
+    fn named_stations() {
+        let mut worker = Worker::default();
+        worker.register(
+            "double",
+            station_fn(|val: i32| async move { Ok(vec![WorkOutput::Result(val * 2)]) }),
+        );
+
+        let ret = futures::executor::block_on(worker.run(vec![
+            Work::named(2, "double").chain_named("double"),
+            Work::named(1, "triple"),
+        ]));
+        assert_eq!(ret.len(), 2);
+        assert!(ret.iter().any(|m| m.is_err()));
+        assert!(ret.iter().any(|m| m.as_ref().ok() == Some(&8)));
+    }
+
+    // #[test]
+    // fn tokio_work() {
+    //     tokio::run_async(
+    //         async {
+    //             let http = Http::new();
+
+    //             let worker = Worker::default();
+
+    //             let ret = await!(worker.run(vec![Work::new(
+    //                 "https://distrowatch.com".to_string(),
+    //                 station_fn(async move |val: String| {
+    //                     let url = Url::parse(val.as_str()).unwrap();
+    //                     let req = Request::new(Method::GET, url);
+    //                     let result = await!(Http::new()
+    //                         .chain(HttpResponseReader)
+    //                         .chain(utils::to_string())
+    //                         .execute(req))?;
+    //                     Ok(vec![WorkOutput::Result(result)])
+    //                 }),
+    //             )]));
+
+    //             for r in ret {
+    //                 println!("{:?}", r);
+    //             }
+    //         },
+    //     );
+    // }
+}
@@ -0,0 +1,113 @@
+use super::super::Result;
+use super::{Order, Work};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+/// Holds the pending work of a worker.
+pub trait WorkQueue<V> {
+    /// Queues `work`, returning false when it was skipped because its key was queued before
+    fn push(&mut self, work: Work<V>) -> Result<bool>;
+    /// Takes the next work to run
+    fn pop(&mut self) -> Result<Option<Work<V>>>;
+    /// Marks work taken with `pop` as done
+    fn complete(&mut self, id: u64) -> Result<()>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+struct Queued<V> {
+    key: (i64, i64, i64),
+    work: Work<V>,
+}
+
+impl<V> PartialEq for Queued<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<V> Eq for Queued<V> {}
+
+impl<V> PartialOrd for Queued<V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<V> Ord for Queued<V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// The default, in-memory queue
+pub struct MemoryQueue<V> {
+    order: Order,
+    seq: u64,
+    seen: HashSet<String>,
+    heap: BinaryHeap<Queued<V>>,
+}
+
+impl<V> MemoryQueue<V> {
+    pub fn new(order: Order) -> MemoryQueue<V> {
+        MemoryQueue {
+            order,
+            seq: 0,
+            seen: HashSet::new(),
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Whether work with `key` has been queued
+    pub fn seen(&self, key: &str) -> bool {
+        self.seen.contains(key)
+    }
+
+    #[cfg(feature = "persist")]
+    pub(super) fn mark_seen(&mut self, key: String) {
+        self.seen.insert(key);
+    }
+}
+
+impl<V> WorkQueue<V> for MemoryQueue<V> {
+    fn push(&mut self, mut work: Work<V>) -> Result<bool> {
+        if let Some(key) = &work.key {
+            if !self.seen.insert(key.clone()) {
+                return Ok(false);
+            }
+        }
+
+        // keep ids handed out by an earlier queue, e.g. when resuming
+        let id = match work.id {
+            Some(id) => id,
+            None => self.seq + 1,
+        };
+        self.seq = self.seq.max(id);
+        work.id = Some(id);
+
+        let depth = work.depth.unwrap_or(0) as i64;
+        let seq = id as i64;
+        let priority = work.priority as i64;
+        let key = match self.order {
+            Order::BreadthFirst => (-depth, priority, -seq),
+            Order::DepthFirst => (depth, priority, seq),
+            Order::Priority => (priority, 0, -seq),
+        };
+        self.heap.push(Queued { key, work });
+        Ok(true)
+    }
+
+    fn pop(&mut self) -> Result<Option<Work<V>>> {
+        Ok(self.heap.pop().map(|m| m.work))
+    }
+
+    fn complete(&mut self, _id: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+}
@@ -0,0 +1,212 @@
+use super::super::super::{
+    into_box, ConveyorError, Result, Spawner, SpawnerExt, Station, ThreadSpawner,
+};
+use super::super::{Stations, WorkBox, WorkError, WorkOutput};
+use super::protocol::{receive, Connection, Outbox, Output, ToAgent, ToCoordinator};
+use super::RemoteConfig;
+use futures::Future;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::io::BufReader;
+use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Runs work for a `RemoteWorker`, using the stations registered on it
+pub struct Agent<V> {
+    stations: Stations<V>,
+    slots: usize,
+    config: RemoteConfig,
+    spawner: Arc<dyn Spawner>,
+}
+
+impl<V: Serialize + DeserializeOwned + Send + 'static> Agent<V> {
+    /// An agent running up to `slots` work items at a time
+    pub fn new(slots: usize, config: RemoteConfig) -> Agent<V> {
+        Agent {
+            stations: HashMap::new(),
+            slots,
+            config,
+            spawner: Arc::new(ThreadSpawner),
+        }
+    }
+
+    /// Runs the work on `spawner` instead of a thread per work item
+    pub fn with_spawner<S: Spawner + 'static>(&mut self, spawner: S) -> &mut Self {
+        self.spawner = Arc::new(spawner);
+        self
+    }
+
+    pub fn register<
+        S: AsRef<str>,
+        W: Station<Input = V, Output = Vec<WorkOutput<V>>, Future = F> + 'static + Send + Sync,
+        F: Future<Output = Result<Vec<WorkOutput<V>>>> + Send,
+    >(
+        &mut self,
+        name: S,
+        station: W,
+    ) -> &mut Self {
+        self.stations
+            .insert(name.as_ref().to_string(), Arc::new(into_box(station)));
+        self
+    }
+
+    /// Runs work from the coordinator at `addr` until it closes the connection
+    pub fn connect_tcp<A: ToSocketAddrs>(&self, addr: A) -> Result<()> {
+        let stream = TcpStream::connect(addr).map_err(ConveyorError::new)?;
+        stream.set_nodelay(true).map_err(ConveyorError::new)?;
+        self.serve(Box::new(stream))
+    }
+
+    #[cfg(unix)]
+    pub fn connect_unix<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let stream = UnixStream::connect(path).map_err(ConveyorError::new)?;
+        self.serve(Box::new(stream))
+    }
+
+    fn serve(&self, conn: Box<dyn Connection>) -> Result<()> {
+        conn.set_read_timeout(Some(self.config.timeout))
+            .map_err(ConveyorError::new)?;
+        let mut reader = BufReader::new(conn.try_clone().map_err(ConveyorError::new)?);
+        let outbox = Outbox::start(
+            conn.try_clone().map_err(ConveyorError::new)?,
+            self.config.timeout,
+        )?;
+
+        outbox.send(&ToCoordinator::<()>::Hello {
+            stations: self.stations.keys().cloned().collect(),
+            slots: self.slots,
+        })?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let heartbeat = {
+            let (outbox, stopped) = (outbox.clone(), stopped.clone());
+            let interval = self.config.heartbeat;
+            thread::spawn(move || {
+                while !stopped.load(Ordering::SeqCst) {
+                    if outbox.send(&ToCoordinator::<()>::Heartbeat).is_err() {
+                        break;
+                    }
+                    thread::sleep(interval);
+                }
+            })
+        };
+
+        let ret = self.receive_jobs(&mut reader, outbox);
+        stopped.store(true, Ordering::SeqCst);
+        conn.shutdown();
+        let _ = heartbeat.join();
+        ret
+    }
+
+    fn receive_jobs(
+        &self,
+        reader: &mut BufReader<Box<dyn Connection>>,
+        outbox: Outbox,
+    ) -> Result<()> {
+        let slots = Arc::new(Slots {
+            outbox,
+            spawner: self.spawner.clone(),
+            slots: self.slots.max(1),
+            queued: Mutex::new((0, VecDeque::new())),
+        });
+
+        while let Some(message) = receive(reader)? {
+            // the input is read per job, so a bad one only fails its job
+            let (job, station, data) = match message {
+                ToAgent::<Value>::Assign { job, station, data } => (job, station, data),
+                ToAgent::Heartbeat => continue,
+            };
+
+            let station = self
+                .stations
+                .get(&station)
+                .cloned()
+                .ok_or_else(|| ConveyorError::new(WorkError::UnknownStation(station)));
+            let data = serde_json::from_value::<V>(data).map_err(ConveyorError::new);
+            match station.and_then(|station| data.map(|data| (station, data))) {
+                Ok((station, data)) => Slots::run(&slots, job, station, data),
+                Err(e) => slots.reply(job, Err(e)),
+            }
+        }
+        Ok(())
+    }
+}
+
+type Job<V> = (u64, Arc<WorkBox<V>>, V);
+
+/// Runs up to `slots` jobs at a time on the spawner, queueing the rest
+struct Slots<V> {
+    outbox: Outbox,
+    spawner: Arc<dyn Spawner>,
+    slots: usize,
+    /// The number of running jobs, and the jobs waiting for a slot
+    queued: Mutex<(usize, VecDeque<Job<V>>)>,
+}
+
+impl<V: Serialize + Send + 'static> Slots<V> {
+    fn run(this: &Arc<Self>, job: u64, station: Arc<WorkBox<V>>, data: V) {
+        {
+            let mut queued = this.queued.lock().unwrap();
+            if queued.0 >= this.slots {
+                queued.1.push_back((job, station, data));
+                return;
+            }
+            queued.0 += 1;
+        }
+        Slots::start(this.clone(), job, station, data);
+    }
+
+    fn start(this: Arc<Self>, job: u64, station: Arc<WorkBox<V>>, data: V) {
+        let spawner = this.spawner.clone();
+        let slots = this.clone();
+        let ret = spawner.spawn(async move {
+            let mut next = Some((job, station, data));
+            // keep the slot for the queued jobs rather than spawning again
+            while let Some((job, station, data)) = next.take() {
+                let ret = station.execute(data).await;
+                slots.reply(job, ret);
+                next = slots.next();
+            }
+        });
+        if let Err(e) = ret {
+            this.reply(job, Err(e));
+            if let Some((job, station, data)) = this.next() {
+                Slots::start(this, job, station, data);
+            }
+        }
+    }
+
+    /// The next queued job, or frees the slot when there is none
+    fn next(&self) -> Option<Job<V>> {
+        let mut queued = self.queued.lock().unwrap();
+        let next = queued.1.pop_front();
+        if next.is_none() {
+            queued.0 -= 1;
+        }
+        next
+    }
+
+    fn reply(&self, job: u64, ret: Result<Vec<WorkOutput<V>>>) {
+        let outputs = ret.and_then(|outputs| {
+            outputs
+                .into_iter()
+                .map(Output::from_output)
+                .collect::<Result<Vec<_>>>()
+        });
+        let _ = match outputs {
+            Ok(outputs) => self.outbox.send(&ToCoordinator::Done { job, outputs }),
+            Err(e) => self.outbox.send(&ToCoordinator::<()>::Failed {
+                job,
+                error: e.to_string(),
+            }),
+        };
+    }
+}
@@ -0,0 +1,372 @@
+use super::super::super::{ConveyorError, Result, Station};
+use super::super::{WorkError, WorkOutput};
+use super::protocol::{receive, Connection, Listener, Outbox, Output, ToAgent, ToCoordinator};
+use super::RemoteConfig;
+use futures::channel::oneshot;
+use futures::future::BoxFuture;
+use futures::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::BufReader;
+use std::marker::PhantomData;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Reply = std::result::Result<Vec<Output<Value>>, String>;
+
+struct Job {
+    station: String,
+    data: Value,
+    reply: oneshot::Sender<Reply>,
+    /// The number of agents lost while running it
+    lost: usize,
+}
+
+struct Agent {
+    conn: Box<dyn Connection>,
+    outbox: Outbox,
+    stations: HashSet<String>,
+    slots: usize,
+    jobs: HashSet<u64>,
+}
+
+#[derive(Default)]
+struct State {
+    /// How many agents a job may be lost with before it fails
+    attempts: usize,
+    agents: HashMap<u64, Agent>,
+    jobs: HashMap<u64, Job>,
+    /// Jobs waiting for an agent with a free slot
+    pending: VecDeque<u64>,
+    seq: u64,
+}
+
+impl State {
+    fn dispatch(&mut self) {
+        let mut waiting = VecDeque::new();
+        let mut lost = Vec::new();
+
+        while let Some(id) = self.pending.pop_front() {
+            let job = match self.jobs.get(&id) {
+                Some(job) if !job.reply.is_canceled() => job,
+                _ => {
+                    self.jobs.remove(&id);
+                    continue;
+                }
+            };
+            let agent = self
+                .agents
+                .iter_mut()
+                .filter(|(_, agent)| {
+                    agent.jobs.len() < agent.slots && agent.stations.contains(&job.station)
+                })
+                .min_by_key(|(_, agent)| agent.jobs.len());
+            let (agent_id, agent) = match agent {
+                Some(agent) => agent,
+                None => {
+                    waiting.push_back(id);
+                    continue;
+                }
+            };
+
+            let message = ToAgent::Assign {
+                job: id,
+                station: job.station.clone(),
+                data: &job.data,
+            };
+            if agent.outbox.send(&message).is_ok() {
+                agent.jobs.insert(id);
+            } else {
+                lost.push(*agent_id);
+                waiting.push_front(id);
+            }
+        }
+
+        self.pending = waiting;
+        for agent in lost {
+            self.lose(agent);
+        }
+    }
+
+    /// Drops an agent, handing its jobs to the others. Jobs which were lost
+    /// with too many agents fail instead, as they may be what kills them.
+    fn lose(&mut self, agent: u64) {
+        if let Some(agent) = self.agents.remove(&agent) {
+            agent.conn.shutdown();
+            for id in agent.jobs {
+                let job = match self.jobs.get_mut(&id) {
+                    Some(job) => job,
+                    None => continue,
+                };
+                job.lost += 1;
+                if job.lost < self.attempts {
+                    self.pending.push_front(id);
+                } else if let Some(job) = self.jobs.remove(&id) {
+                    let error = format!("lost {} agents running it", job.lost);
+                    let _ = job.reply.send(Err(error));
+                }
+            }
+            self.dispatch();
+        }
+    }
+
+    fn reply(&mut self, agent: u64, job: u64, reply: Reply) {
+        if let Some(agent) = self.agents.get_mut(&agent) {
+            agent.jobs.remove(&job);
+        }
+        if let Some(job) = self.jobs.remove(&job) {
+            let _ = job.reply.send(reply);
+        }
+        self.dispatch();
+    }
+}
+
+struct Hub {
+    config: RemoteConfig,
+    state: Mutex<State>,
+    closed: AtomicBool,
+}
+
+impl Hub {
+    fn accept(self: Arc<Self>, listener: Listener) {
+        let mut agents = 0;
+        while let Ok(conn) = listener.accept() {
+            if self.closed.load(Ordering::SeqCst) {
+                break;
+            }
+            agents += 1;
+            let hub = self.clone();
+            thread::spawn(move || hub.serve(agents, conn));
+        }
+    }
+
+    /// Reads the messages of an agent until it's lost
+    fn serve(&self, id: u64, conn: Box<dyn Connection>) {
+        let _ = self.serve_agent(id, conn);
+        self.state.lock().unwrap().lose(id);
+    }
+
+    fn serve_agent(&self, id: u64, conn: Box<dyn Connection>) -> Result<()> {
+        conn.set_read_timeout(Some(self.config.timeout))
+            .map_err(ConveyorError::new)?;
+        let mut reader = BufReader::new(conn.try_clone().map_err(ConveyorError::new)?);
+
+        match receive(&mut reader)? {
+            Some(ToCoordinator::<Value>::Hello { stations, slots }) => {
+                let writer = conn.try_clone().map_err(ConveyorError::new)?;
+                let outbox = Outbox::start(writer, self.config.timeout)?;
+                let mut state = self.state.lock().unwrap();
+                if self.closed.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+                state.agents.insert(
+                    id,
+                    Agent {
+                        conn,
+                        outbox,
+                        stations: stations.into_iter().collect(),
+                        slots: slots.max(1),
+                        jobs: HashSet::new(),
+                    },
+                );
+                state.dispatch();
+            }
+            _ => return Ok(()),
+        }
+
+        while let Some(message) = receive(&mut reader)? {
+            match message {
+                ToCoordinator::Done { job, outputs } => {
+                    self.state.lock().unwrap().reply(id, job, Ok(outputs))
+                }
+                ToCoordinator::Failed { job, error } => {
+                    self.state.lock().unwrap().reply(id, job, Err(error))
+                }
+                ToCoordinator::Heartbeat | ToCoordinator::Hello { .. } => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn heartbeat(&self) {
+        while !self.closed.load(Ordering::SeqCst) {
+            thread::sleep(self.config.heartbeat);
+            let mut state = self.state.lock().unwrap();
+            let lost = state
+                .agents
+                .iter()
+                .filter(|(_, agent)| agent.outbox.send(&ToAgent::<()>::Heartbeat).is_err())
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+            for agent in lost {
+                state.lose(agent);
+            }
+        }
+    }
+
+    fn submit(&self, station: String, data: Value) -> oneshot::Receiver<Reply> {
+        let (reply, receiver) = oneshot::channel();
+        if self.closed.load(Ordering::SeqCst) {
+            return receiver;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.seq += 1;
+        let id = state.seq;
+        state.jobs.insert(
+            id,
+            Job {
+                station,
+                data,
+                reply,
+                lost: 0,
+            },
+        );
+        state.pending.push_back(id);
+        state.dispatch();
+        receiver
+    }
+}
+
+enum Address {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// Hands work to agent processes connected over TCP or a Unix socket.
+///
+/// Register a `station` for every name the agents provide on a `Worker`,
+/// and it runs the work as usual, with the stations running on the agents.
+/// Work assigned to an agent which stops sending heartbeats or disconnects
+/// goes to another agent.
+pub struct RemoteWorker {
+    hub: Arc<Hub>,
+    address: Address,
+}
+
+impl RemoteWorker {
+    pub fn bind_tcp<A: ToSocketAddrs>(addr: A, config: RemoteConfig) -> Result<RemoteWorker> {
+        let listener = TcpListener::bind(addr).map_err(ConveyorError::new)?;
+        let address = Address::Tcp(listener.local_addr().map_err(ConveyorError::new)?);
+        Ok(RemoteWorker::start(
+            Listener::Tcp(listener),
+            address,
+            config,
+        ))
+    }
+
+    #[cfg(unix)]
+    pub fn bind_unix<P: AsRef<Path>>(path: P, config: RemoteConfig) -> Result<RemoteWorker> {
+        let listener = UnixListener::bind(path.as_ref()).map_err(ConveyorError::new)?;
+        let address = Address::Unix(path.as_ref().to_path_buf());
+        Ok(RemoteWorker::start(
+            Listener::Unix(listener),
+            address,
+            config,
+        ))
+    }
+
+    fn start(listener: Listener, address: Address, config: RemoteConfig) -> RemoteWorker {
+        let hub = Arc::new(Hub {
+            config,
+            state: Mutex::new(State {
+                attempts: config.attempts.max(1),
+                ..State::default()
+            }),
+            closed: AtomicBool::new(false),
+        });
+
+        let accept = hub.clone();
+        thread::spawn(move || accept.accept(listener));
+        let heartbeat = hub.clone();
+        thread::spawn(move || heartbeat.heartbeat());
+
+        RemoteWorker { hub, address }
+    }
+
+    /// The address agents connect to, when listening on TCP
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match &self.address {
+            Address::Tcp(addr) => Some(*addr),
+            #[cfg(unix)]
+            Address::Unix(_) => None,
+        }
+    }
+
+    /// The number of connected agents
+    pub fn agents(&self) -> usize {
+        self.hub.state.lock().unwrap().agents.len()
+    }
+
+    /// A station running the agents' station `name`
+    pub fn station<V, S: Into<String>>(&self, name: S) -> RemoteStation<V> {
+        RemoteStation {
+            hub: self.hub.clone(),
+            name: name.into(),
+            _v: PhantomData,
+        }
+    }
+}
+
+impl Drop for RemoteWorker {
+    fn drop(&mut self) {
+        self.hub.closed.store(true, Ordering::SeqCst);
+        {
+            let mut state = self.hub.state.lock().unwrap();
+            for (_, agent) in state.agents.drain() {
+                agent.conn.shutdown();
+            }
+            state.jobs.clear();
+            state.pending.clear();
+        }
+
+        // wake up the accepting thread so it sees it's closed
+        match &self.address {
+            Address::Tcp(addr) => {
+                let _ = TcpStream::connect(addr);
+            }
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                let _ = UnixStream::connect(path);
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+pub struct RemoteStation<V> {
+    hub: Arc<Hub>,
+    name: String,
+    _v: PhantomData<fn(V) -> V>,
+}
+
+impl<V: Serialize + DeserializeOwned + Send + 'static> Station for RemoteStation<V> {
+    type Input = V;
+    type Output = Vec<WorkOutput<V>>;
+    type Future = BoxFuture<'static, Result<Vec<WorkOutput<V>>>>;
+
+    fn execute(&self, input: V) -> Self::Future {
+        let data = match serde_json::to_value(input) {
+            Ok(data) => data,
+            Err(e) => return future::ready(Err(ConveyorError::new(e))).boxed(),
+        };
+        self.hub
+            .submit(self.name.clone(), data)
+            .map(|reply| match reply {
+                Ok(Ok(outputs)) => outputs.into_iter().map(Output::into_output).collect(),
+                Ok(Err(error)) => Err(ConveyorError::new(WorkError::Remote(error))),
+                Err(_) => Err(ConveyorError::new(WorkError::Remote(
+                    "coordinator closed".to_string(),
+                ))),
+            })
+            .boxed()
+    }
+}
@@ -0,0 +1,288 @@
+//! Runs work on agent processes, see `RemoteWorker` and `Agent`.
+//!
+//! Coordinator and agents exchange JSON messages, one per line. Agents
+//! introduce themselves with the stations they provide and how much work
+//! they run at a time, after which the coordinator assigns work to them
+//! by station name and input. Both sides send heartbeats, and give up on
+//! the other side when none arrive within the timeout.
+
+mod agent;
+mod coordinator;
+mod protocol;
+
+pub use self::agent::*;
+pub use self::coordinator::*;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RemoteConfig {
+    /// How often to send a heartbeat
+    pub heartbeat: Duration,
+    /// How long to wait for a message before the other side is considered lost
+    pub timeout: Duration,
+    /// How many agents a work item may be lost with before it fails
+    pub attempts: usize,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> RemoteConfig {
+        RemoteConfig {
+            heartbeat: Duration::from_secs(1),
+            timeout: Duration::from_secs(5),
+            attempts: 3,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use super::super::*;
+    use super::*;
+    use futures::executor::block_on;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    fn config() -> RemoteConfig {
+        RemoteConfig {
+            heartbeat: Duration::from_millis(20),
+            timeout: Duration::from_millis(200),
+            attempts: 2,
+        }
+    }
+
+    fn agent() -> Agent<u32> {
+        let mut agent = Agent::new(2, config());
+        agent.register(
+            "tree",
+            station_fn(|val: u32| async move {
+                let mut out = vec![WorkOutput::Result(val)];
+                if val < 10 {
+                    out.push(WorkOutput::Work(Work::named(val * 10 + 1, "tree")));
+                    out.push(WorkOutput::Work(Work::named(val * 10 + 2, "tree")));
+                }
+                Ok(out)
+            }),
+        );
+        agent
+    }
+
+    fn run(remote: &RemoteWorker) -> thread::JoinHandle<Vec<u32>> {
+        let mut worker = Worker::default();
+        worker.register("tree", remote.station("tree"));
+        thread::spawn(move || {
+            let mut ret = block_on(worker.run(vec![Work::named(1, "tree")]))
+                .into_iter()
+                .map(|m| m.unwrap())
+                .collect::<Vec<_>>();
+            ret.sort();
+            ret
+        })
+    }
+
+    #[test]
+    fn tcp() {
+        let remote = RemoteWorker::bind_tcp("127.0.0.1:0", config()).unwrap();
+        let addr = remote.local_addr().unwrap();
+        let agents = (0..2)
+            .map(|_| thread::spawn(move || agent().connect_tcp(addr)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(run(&remote).join().unwrap(), vec![1, 11, 12]);
+
+        drop(remote);
+        for agent in agents {
+            agent.join().unwrap().unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix() {
+        let path =
+            std::env::temp_dir().join(format!("conveyor-remote-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let remote = RemoteWorker::bind_unix(&path, config()).unwrap();
+        let agent = {
+            let path = path.clone();
+            thread::spawn(move || agent().connect_unix(path))
+        };
+
+        assert_eq!(run(&remote).join().unwrap(), vec![1, 11, 12]);
+        drop(remote);
+        agent.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn reassign() {
+        let remote = RemoteWorker::bind_tcp("127.0.0.1:0", config()).unwrap();
+        let addr = remote.local_addr().unwrap();
+
+        // an agent which takes the work and then goes quiet
+        let mut silent = TcpStream::connect(addr).unwrap();
+        silent
+            .write_all(b"{\"type\":\"hello\",\"stations\":[\"tree\"],\"slots\":1}\n")
+            .unwrap();
+        while remote.agents() == 0 {
+            thread::sleep(Duration::from_millis(5));
+        }
+        let ret = run(&remote);
+        let mut line = String::new();
+        let mut reader = BufReader::new(silent.try_clone().unwrap());
+        while !line.contains("assign") {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+
+        let agent = thread::spawn(move || agent().connect_tcp(addr));
+        assert_eq!(ret.join().unwrap(), vec![1, 11, 12]);
+        assert_eq!(remote.agents(), 1);
+
+        drop(remote);
+        agent.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn slots() {
+        use std::net::TcpListener;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (running, most) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let mut agent = Agent::new(2, config());
+        {
+            let (running, most) = (running.clone(), most.clone());
+            agent.register(
+                "sleep",
+                station_fn(move |val: u32| {
+                    let (running, most) = (running.clone(), most.clone());
+                    async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        most.fetch_max(now, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(10));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok(vec![WorkOutput::Result(val)])
+                    }
+                }),
+            );
+        }
+        let agent = thread::spawn(move || agent.connect_tcp(addr));
+
+        // a coordinator assigning more work than the agent has slots for
+        let (mut conn, _) = listener.accept().unwrap();
+        for job in 0..6 {
+            let line = format!(
+                "{{\"type\":\"assign\",\"job\":{},\"station\":\"sleep\",\"data\":{}}}\n",
+                job, job
+            );
+            conn.write_all(line.as_bytes()).unwrap();
+        }
+        let mut reader = BufReader::new(conn.try_clone().unwrap());
+        let (mut line, mut done) = (String::new(), 0);
+        while done < 6 {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            if line.contains("done") {
+                done += 1;
+            }
+        }
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+
+        drop((conn, reader));
+        agent.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn bad_input() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let agent = thread::spawn(move || agent().connect_tcp(addr));
+        let (mut conn, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(conn.try_clone().unwrap());
+        let mut next = |kind: &str| {
+            let mut line = String::new();
+            while !line.contains(kind) {
+                line.clear();
+                let n = reader.read_line(&mut line).unwrap();
+                assert!(n > 0, "the agent disconnected");
+            }
+            line
+        };
+
+        conn.write_all(b"{\"type\":\"assign\",\"job\":1,\"station\":\"tree\",\"data\":\"one\"}\n")
+            .unwrap();
+        assert!(next("\"job\":1").contains("failed"));
+        // the agent is still connected and runs the next work
+        conn.write_all(b"{\"type\":\"assign\",\"job\":2,\"station\":\"tree\",\"data\":20}\n")
+            .unwrap();
+        assert!(next("\"job\":2").contains("done"));
+
+        drop((conn, reader));
+        agent.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn attempts() {
+        let remote = RemoteWorker::bind_tcp("127.0.0.1:0", config()).unwrap();
+        let addr = remote.local_addr().unwrap();
+
+        // agents which disconnect once they're given work
+        let crashing = thread::spawn(move || {
+            for _ in 0..2 {
+                let mut conn = TcpStream::connect(addr).unwrap();
+                conn.write_all(b"{\"type\":\"hello\",\"stations\":[\"tree\"],\"slots\":1}\n")
+                    .unwrap();
+                let mut reader = BufReader::new(conn.try_clone().unwrap());
+                let mut line = String::new();
+                while !line.contains("assign") {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                }
+            }
+        });
+
+        let station = remote.station::<u32, _>("tree");
+        let ret = block_on(station.execute(1));
+        crashing.join().unwrap();
+        match ret {
+            Err(e) => assert!(e.to_string().contains("lost 2 agents")),
+            Ok(_) => panic!("expected the work to fail"),
+        }
+    }
+
+    #[test]
+    fn example_agent() {
+        use std::process::{Command, Stdio};
+
+        // the example's agent heartbeats at the default interval
+        let remote = RemoteWorker::bind_tcp("127.0.0.1:0", RemoteConfig::default()).unwrap();
+        let addr = remote.local_addr().unwrap();
+        let mut agent = Command::new(env!("CARGO"))
+            .args([
+                "run",
+                "--quiet",
+                "--example",
+                "remote",
+                "--features",
+                "remote",
+            ])
+            .args(["--", "agent", &addr.to_string()])
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let mut worker = Worker::default();
+        worker.register("split", remote.station("split"));
+        let ret = block_on(worker.run(vec![Work::named(8u64, "split")]));
+        assert_eq!(ret.len(), 15);
+
+        drop(remote);
+        assert!(agent.wait().unwrap().success());
+    }
+}
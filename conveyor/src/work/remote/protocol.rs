@@ -0,0 +1,210 @@
+use super::super::super::{ConveyorError, Result};
+use super::super::{StationRef, Work, WorkError, WorkOutput};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Messages from the coordinator to an agent
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(super) enum ToAgent<D> {
+    Assign { job: u64, station: String, data: D },
+    Heartbeat,
+}
+
+/// Messages from an agent to the coordinator
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(super) enum ToCoordinator<D> {
+    Hello { stations: Vec<String>, slots: usize },
+    Heartbeat,
+    Done { job: u64, outputs: Vec<Output<D>> },
+    Failed { job: u64, error: String },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub(super) enum Output<D> {
+    Result { data: D },
+    Work(Task<D>),
+}
+
+/// Child work sent back by an agent
+#[derive(Serialize, Deserialize)]
+pub(super) struct Task<D> {
+    data: D,
+    stations: Vec<String>,
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl<V> Output<V> {
+    pub(super) fn from_output(output: WorkOutput<V>) -> Result<Output<V>> {
+        match output {
+            WorkOutput::Result(data) => Ok(Output::Result { data }),
+            WorkOutput::Work(work) => {
+                let stations = work
+                    .station_names()
+                    .ok_or_else(|| ConveyorError::new(WorkError::NotPersistable))?;
+                Ok(Output::Work(Task {
+                    data: work.data,
+                    stations,
+                    key: work.key,
+                    priority: work.priority,
+                    tags: work.tags,
+                }))
+            }
+        }
+    }
+}
+
+impl Output<serde_json::Value> {
+    pub(super) fn into_output<V: serde::de::DeserializeOwned>(self) -> Result<WorkOutput<V>> {
+        match self {
+            Output::Result { data } => Ok(WorkOutput::Result(
+                serde_json::from_value(data).map_err(ConveyorError::new)?,
+            )),
+            Output::Work(task) => {
+                let data = serde_json::from_value(task.data).map_err(ConveyorError::new)?;
+                let mut stations = task.stations.into_iter().map(StationRef::Named);
+                let station = stations
+                    .next()
+                    .unwrap_or_else(|| StationRef::Named(String::new()));
+                let mut work = Work::with_station(data, station);
+                work.next = stations.collect();
+                work.key = task.key;
+                work.priority = task.priority;
+                work.tags = task.tags;
+                Ok(WorkOutput::Work(work))
+            }
+        }
+    }
+}
+
+/// A socket carrying the protocol, one JSON message per line
+pub(super) trait Connection: Read + Write + Send {
+    fn try_clone(&self) -> io::Result<Box<dyn Connection>>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn shutdown(&self);
+}
+
+impl Connection for TcpStream {
+    fn try_clone(&self) -> io::Result<Box<dyn Connection>> {
+        Ok(Box::new(TcpStream::try_clone(self)?))
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+
+    fn shutdown(&self) {
+        let _ = TcpStream::shutdown(self, Shutdown::Both);
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn try_clone(&self) -> io::Result<Box<dyn Connection>> {
+        Ok(Box::new(UnixStream::try_clone(self)?))
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+
+    fn shutdown(&self) {
+        let _ = UnixStream::shutdown(self, Shutdown::Both);
+    }
+}
+
+pub(super) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    pub(super) fn accept(&self) -> io::Result<Box<dyn Connection>> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nodelay(true)?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => Ok(Box::new(listener.accept()?.0)),
+        }
+    }
+}
+
+fn encode<T: Serialize>(message: &T) -> Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(message).map_err(ConveyorError::new)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Messages waiting to be written to a connection by its writer thread, so
+/// senders never block on the socket
+#[derive(Clone)]
+pub(super) struct Outbox(mpsc::Sender<Vec<u8>>);
+
+impl Outbox {
+    /// Writes to `conn` until the outboxes are dropped. A write which fails
+    /// or takes longer than `timeout` shuts the connection down, so its
+    /// reader sees the other side as lost.
+    pub(super) fn start(conn: Box<dyn Connection>, timeout: Duration) -> Result<Outbox> {
+        conn.set_write_timeout(Some(timeout))
+            .map_err(ConveyorError::new)?;
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+        thread::Builder::new()
+            .spawn(move || {
+                let mut conn = conn;
+                for line in receiver {
+                    if conn.write_all(&line).is_err() {
+                        conn.shutdown();
+                        break;
+                    }
+                }
+            })
+            .map_err(ConveyorError::new)?;
+        Ok(Outbox(sender))
+    }
+
+    /// Queues `message`, failing once the writer has stopped
+    pub(super) fn send<T: Serialize>(&self, message: &T) -> Result<()> {
+        self.0
+            .send(encode(message)?)
+            .map_err(|_| ConveyorError::new(WorkError::Remote("connection closed".to_string())))
+    }
+}
+
+/// Reads the next message, or None once the other side closed the connection
+pub(super) fn receive<T: serde::de::DeserializeOwned, R: BufRead>(
+    reader: &mut R,
+) -> Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).map_err(ConveyorError::new)? == 0 {
+        return Ok(None);
+    }
+    serde_json::from_str(&line)
+        .map(Some)
+        .map_err(ConveyorError::new)
+}
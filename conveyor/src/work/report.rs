@@ -0,0 +1,191 @@
+use super::Skipped;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How many of the slowest items a report keeps
+const SLOWEST: usize = 10;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StationStats {
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Time spent in the station, over all its runs
+    pub duration: Duration,
+}
+
+impl StationStats {
+    pub fn average(&self) -> Duration {
+        match self.succeeded + self.failed {
+            0 => Duration::default(),
+            runs => self.duration / runs as u32,
+        }
+    }
+}
+
+/// A single run of a work item
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStats {
+    pub id: Option<u64>,
+    pub station: String,
+    pub depth: usize,
+    pub duration: Duration,
+    pub succeeded: bool,
+}
+
+/// Statistics of a worker run. Every attempt of a retried item counts as a run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub retried: usize,
+    /// Results yielded so far
+    pub results: usize,
+    pub running: usize,
+    pub queued: usize,
+    pub stations: BTreeMap<String, StationStats>,
+    /// Runs per depth
+    pub depths: BTreeMap<usize, usize>,
+    /// The slowest runs, slowest first
+    pub slowest: Vec<ItemStats>,
+    pub skipped: Skipped,
+    pub elapsed: Duration,
+    pub finished: bool,
+}
+
+impl Report {
+    pub(super) fn record(&mut self, item: ItemStats) {
+        let station = self.stations.entry(item.station.clone()).or_default();
+        station.duration += item.duration;
+        if item.succeeded {
+            station.succeeded += 1;
+            self.succeeded += 1;
+        } else {
+            station.failed += 1;
+            self.failed += 1;
+        }
+        *self.depths.entry(item.depth).or_default() += 1;
+
+        let idx = self
+            .slowest
+            .iter()
+            .position(|m| m.duration < item.duration)
+            .unwrap_or(self.slowest.len());
+        if idx < SLOWEST {
+            self.slowest.insert(idx, item);
+            self.slowest.truncate(SLOWEST);
+        }
+    }
+}
+
+struct Inner {
+    report: Report,
+    start: Option<Instant>,
+}
+
+/// A live view of a run's report, which can be moved to another task
+#[derive(Clone)]
+pub struct ReportHandle {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl ReportHandle {
+    pub(super) fn new() -> ReportHandle {
+        ReportHandle {
+            inner: Arc::new(Mutex::new(Inner {
+                report: Report::default(),
+                start: None,
+            })),
+        }
+    }
+
+    /// The report as it is now
+    pub fn snapshot(&self) -> Report {
+        let inner = self.inner.lock().unwrap();
+        let mut report = inner.report.clone();
+        if let (false, Some(start)) = (report.finished, inner.start) {
+            report.elapsed = start.elapsed();
+        }
+        report
+    }
+
+    pub(super) fn update<F: FnOnce(&mut Report)>(&self, f: F) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.start.is_none() {
+            inner.start = Some(Instant::now());
+        }
+        f(&mut inner.report);
+    }
+
+    pub(super) fn finish(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.report.finished {
+            return;
+        }
+        if let Some(start) = inner.start {
+            inner.report.elapsed = start.elapsed();
+        }
+        inner.report.finished = true;
+        inner.report.running = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::*;
+    use super::super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn report() {
+        let mut worker = Worker::new(WorkerConfig {
+            concurrency: 1,
+            ..Default::default()
+        });
+        worker.register(
+            "tree",
+            station_fn(|val: i32| async move {
+                if val == 12 {
+                    return Err(ConveyorError::new(std::io::Error::other("failed")));
+                }
+                let mut out = vec![WorkOutput::Result(val)];
+                if val < 10 {
+                    out.push(WorkOutput::Work(Work::named(val * 10 + 1, "tree")));
+                    out.push(WorkOutput::Work(Work::named(val * 10 + 2, "tree")));
+                }
+                Ok(out)
+            }),
+        );
+        worker.register(
+            "double",
+            station_fn(|val: i32| async move { Ok(vec![WorkOutput::Result(val * 2)]) }),
+        );
+
+        let mut stream = worker.stream(vec![Work::named(1, "tree"), Work::named(5, "double")]);
+        let report = stream.report();
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), 1);
+        let live = report.snapshot();
+        assert!(!live.finished);
+        assert_eq!(live.succeeded, 1);
+        assert_eq!(live.results, 1);
+        assert_eq!(live.queued, 3);
+
+        block_on(stream.collect::<Vec<_>>());
+        let report = report.snapshot();
+        assert!(report.finished);
+        assert_eq!((report.succeeded, report.failed, report.results), (3, 1, 3));
+        assert_eq!(report.stations["tree"].succeeded, 2);
+        assert_eq!(report.stations["tree"].failed, 1);
+        assert_eq!(report.stations["double"].succeeded, 1);
+        assert_eq!(report.depths[&0], 2);
+        assert_eq!(report.depths[&1], 2);
+        assert_eq!(report.slowest.len(), 4);
+        assert!(report
+            .slowest
+            .windows(2)
+            .all(|m| m[0].duration >= m[1].duration));
+
+        let (_, report) = block_on(worker.run_with_report(vec![Work::named(1, "tree")]));
+        assert_eq!((report.succeeded, report.failed, report.running), (2, 1, 0));
+    }
+}
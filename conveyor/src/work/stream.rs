@@ -0,0 +1,445 @@
+use super::super::{Clock, ConveyorError, Result};
+use super::failure::{Failed, Recover};
+use super::limits::Deadline;
+use super::report::{ItemStats, ReportHandle};
+use super::{
+    ErrorPolicy, Lineage, MemoryQueue, Skipped, Stations, Work, WorkError, WorkOutput, WorkQueue,
+    WorkResult, WorkerConfig,
+};
+use futures::prelude::*;
+use futures::stream::FuturesUnordered;
+use pin_project::pin_project;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+type Running<V> =
+    Pin<Box<dyn Future<Output = (usize, Lineage, Result<Vec<WorkOutput<V>>>)> + Send>>;
+
+struct InFlight<V> {
+    /// A copy of the work, to retry or dead-letter it if it fails
+    backup: Option<Work<V>>,
+    station: String,
+    at: Instant,
+}
+
+/// Everything but the running work, so it can be borrowed alongside it
+struct State<V, Q> {
+    config: WorkerConfig,
+    recover: Option<Recover<V>>,
+    queue: Q,
+    retry: VecDeque<Work<V>>,
+    in_flight: HashMap<usize, InFlight<V>>,
+    token: usize,
+    report: ReportHandle,
+    output: VecDeque<Result<WorkResult<V>>>,
+    started: usize,
+    skipped: Skipped,
+    /// Child work queued per key group, see `WorkerConfig::max_fan_out`
+    fan_out: HashMap<String, usize>,
+    /// Initial work whose branch failed, see `ErrorPolicy::SkipBranch`
+    failed_roots: HashSet<u64>,
+    aborted: bool,
+    done: bool,
+}
+
+/// The id of the initial work `id` descends from
+fn root(id: Option<u64>, ancestors: &[u64]) -> Option<u64> {
+    ancestors.first().cloned().or(id)
+}
+
+impl<V, Q: WorkQueue<V>> State<V, Q> {
+    fn next_work(&mut self) -> Result<Option<Work<V>>> {
+        while let Some(work) = self.retry.pop_front() {
+            if !self.skip_branch(work.id, &work.ancestors)? {
+                return Ok(Some(work));
+            }
+        }
+        loop {
+            if let Some(max) = self.config.max_items {
+                if self.started >= max {
+                    return Ok(None);
+                }
+            }
+            let work = match self.queue.pop()? {
+                Some(work) => work,
+                None => return Ok(None),
+            };
+            if !self.skip_branch(work.id, &work.ancestors)? {
+                self.started += 1;
+                return Ok(Some(work));
+            }
+        }
+    }
+
+    /// Drops work from a failed branch, returning whether it was dropped
+    fn skip_branch(&mut self, id: Option<u64>, ancestors: &[u64]) -> Result<bool> {
+        match root(id, ancestors) {
+            Some(root) if self.failed_roots.contains(&root) => {
+                self.skipped.branch += 1;
+                if let Some(id) = id {
+                    self.queue.complete(id)?;
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Keeps track of `work`, returning the token to finish it with
+    fn start(&mut self, work: &Work<V>) -> usize {
+        self.token += 1;
+        self.in_flight.insert(
+            self.token,
+            InFlight {
+                backup: self
+                    .recover
+                    .as_ref()
+                    .map(|recover| work.duplicate(recover.clone)),
+                station: work.station_name().to_string(),
+                at: Instant::now(),
+            },
+        );
+        let (running, queued) = (self.in_flight.len(), self.queue.len() + self.retry.len());
+        self.report.update(|report| {
+            report.running = running;
+            report.queued = queued;
+        });
+        self.token
+    }
+
+    /// Drops all running work, returning how much there was
+    fn stop(&mut self, running: usize) -> usize {
+        self.in_flight.clear();
+        self.done = true;
+        running + self.queue.len() + self.retry.len()
+    }
+
+    /// Called once the stream has ended
+    fn close(&mut self) {
+        let skipped = self.skipped;
+        self.report.update(|report| report.skipped = skipped);
+        self.report.finish();
+    }
+
+    fn finish(&mut self, token: usize, lineage: Lineage, ret: Result<Vec<WorkOutput<V>>>) {
+        let item = self.in_flight.get(&token).map(|item| ItemStats {
+            id: lineage.id,
+            station: item.station.clone(),
+            depth: lineage.depth,
+            duration: item.at.elapsed(),
+            succeeded: ret.is_ok(),
+        });
+        let results = self.output.len();
+        let retried = self.finish_work(token, lineage, ret);
+        self.in_flight.remove(&token);
+
+        let (running, queued) = (self.in_flight.len(), self.queue.len() + self.retry.len());
+        let results = self
+            .output
+            .iter()
+            .skip(results)
+            .filter(|m| m.is_ok())
+            .count();
+        let skipped = self.skipped;
+        self.report.update(|report| {
+            if let Some(item) = item {
+                report.record(item);
+            }
+            if retried {
+                report.retried += 1;
+            }
+            report.results += results;
+            report.running = running;
+            report.queued = queued;
+            report.skipped = skipped;
+        });
+    }
+
+    /// Returns whether the work is retried
+    fn finish_work(
+        &mut self,
+        token: usize,
+        lineage: Lineage,
+        ret: Result<Vec<WorkOutput<V>>>,
+    ) -> bool {
+        let item = self.in_flight.get_mut(&token);
+        let backup = item.and_then(|m| m.backup.take());
+        let root = root(lineage.id, &lineage.ancestors);
+        match ret {
+            // the branch failed while this was running
+            Ok(_) if root.is_some_and(|root| self.failed_roots.contains(&root)) => {
+                self.skipped.branch += 1;
+            }
+            Ok(outputs) => self.queue_outputs(&lineage, outputs),
+            Err(e) => {
+                if let Some(mut backup) = backup {
+                    let retries = self.recover.as_ref().map(|m| m.retries).unwrap_or(0);
+                    if backup.attempts < retries {
+                        backup.attempts += 1;
+                        self.retry.push_back(backup);
+                        return true;
+                    }
+                    self.fail(Some(backup), e);
+                } else {
+                    self.fail(None, e);
+                }
+                if self.config.on_error == ErrorPolicy::SkipBranch {
+                    self.failed_roots.extend(root);
+                }
+            }
+        }
+
+        // children are queued before the work is marked as done, so a
+        // crash in between runs it again rather than losing its children
+        if let Some(id) = lineage.id {
+            if let Err(e) = self.queue.complete(id) {
+                self.output.push_back(Err(e));
+            }
+        }
+        false
+    }
+
+    fn queue_outputs(&mut self, lineage: &Lineage, outputs: Vec<WorkOutput<V>>) {
+        for next in outputs {
+            let mut work = match next {
+                WorkOutput::Result(value) => {
+                    self.output.push_back(Ok(WorkResult {
+                        value,
+                        lineage: lineage.clone(),
+                    }));
+                    continue;
+                }
+                WorkOutput::Work(work) => work,
+            };
+
+            // chained work arrives with its lineage already set
+            if work.depth.is_none() {
+                work.depth = Some(lineage.depth + 1);
+                work.ancestors = lineage.ancestors.clone();
+                work.ancestors.extend(lineage.id);
+            }
+            if let Some(max) = self.config.max_depth {
+                if work.depth() > max {
+                    self.skipped.depth += 1;
+                    continue;
+                }
+            }
+            let group = match (self.config.max_fan_out, &work.key) {
+                (Some(max), Some(key)) => {
+                    let group = (self.config.key_group)(key);
+                    if self.fan_out.get(group).is_some_and(|queued| *queued >= max) {
+                        self.skipped.fan_out += 1;
+                        continue;
+                    }
+                    Some(group.to_string())
+                }
+                _ => None,
+            };
+            match self.queue.push(work) {
+                // work skipped for a key queued before doesn't count
+                Ok(true) => {
+                    if let Some(group) = group {
+                        *self.fan_out.entry(group).or_insert(0) += 1;
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => self.output.push_back(Err(e)),
+            }
+        }
+    }
+
+    fn fail(&mut self, work: Option<Work<V>>, error: ConveyorError) {
+        let dead_letter = self.recover.as_ref().and_then(|m| m.dead_letter.clone());
+        let error = match (dead_letter, work) {
+            (Some(dead_letter), Some(work)) => {
+                let message = error.to_string();
+                dead_letter.push(Failed { work, error });
+                match self.config.on_error {
+                    ErrorPolicy::Abort => ConveyorError::new(WorkError::Aborted(message)),
+                    _ => return,
+                }
+            }
+            _ => error,
+        };
+
+        match self.config.on_error {
+            ErrorPolicy::Continue => self.output.push_back(Err(error)),
+            ErrorPolicy::SkipBranch => {}
+            ErrorPolicy::Abort => {
+                self.output.push_back(Err(error));
+                self.aborted = true;
+            }
+        }
+    }
+}
+
+#[pin_project]
+pub struct WorkStream<V, Q = MemoryQueue<V>> {
+    stations: Stations<V>,
+    running: FuturesUnordered<Running<V>>,
+    deadline: Option<Deadline>,
+    clock: Arc<dyn Clock>,
+    state: State<V, Q>,
+}
+
+impl<V, Q> WorkStream<V, Q> {
+    pub(super) fn new(
+        config: WorkerConfig,
+        stations: Stations<V>,
+        recover: Option<Recover<V>>,
+        clock: Arc<dyn Clock>,
+        queue: Q,
+        output: VecDeque<Result<WorkResult<V>>>,
+    ) -> WorkStream<V, Q> {
+        WorkStream {
+            stations,
+            running: FuturesUnordered::new(),
+            deadline: None,
+            clock,
+            state: State {
+                config,
+                recover,
+                queue,
+                retry: VecDeque::new(),
+                in_flight: HashMap::new(),
+                token: 0,
+                report: ReportHandle::new(),
+                output,
+                started: 0,
+                skipped: Skipped::default(),
+                fan_out: HashMap::new(),
+                failed_roots: HashSet::new(),
+                aborted: false,
+                done: false,
+            },
+        }
+    }
+
+    pub fn queue(&self) -> &Q {
+        &self.state.queue
+    }
+
+    /// What was left out because of the worker's limits, complete once the stream ended
+    pub fn skipped(&self) -> &Skipped {
+        &self.state.skipped
+    }
+
+    /// A live report of the run, which stays valid after the stream is dropped
+    pub fn report(&self) -> ReportHandle {
+        self.state.report.clone()
+    }
+
+    /// Yields results together with their lineage
+    pub fn traced(self) -> TracedWorkStream<V, Q> {
+        TracedWorkStream { inner: self }
+    }
+}
+
+impl<V: 'static, Q: WorkQueue<V>> WorkStream<V, Q> {
+    fn poll_result(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<WorkResult<V>>>> {
+        let this = self.project();
+        let state = this.state;
+
+        if let (None, Some(budget)) = (&this.deadline, state.config.time_budget) {
+            *this.deadline = Some(Deadline::new(&**this.clock, budget));
+        }
+
+        loop {
+            if let Some(next) = state.output.pop_front() {
+                return Poll::Ready(Some(next));
+            }
+            if state.done {
+                state.close();
+                return Poll::Ready(None);
+            }
+
+            if state.aborted {
+                state.skipped.aborted += state.stop(this.running.len());
+                this.running.clear();
+                continue;
+            }
+
+            if this
+                .deadline
+                .as_mut()
+                .map(|m| m.expired(cx))
+                .unwrap_or(false)
+            {
+                state.skipped.time += state.stop(this.running.len());
+                this.running.clear();
+                continue;
+            }
+
+            while state.config.concurrency == 0 || this.running.len() < state.config.concurrency {
+                let work = match state.next_work() {
+                    Ok(Some(work)) => work,
+                    Ok(None) => break,
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                };
+                let lineage = work.lineage();
+                let token = state.start(&work);
+                match work.execute(this.stations) {
+                    Ok(fut) => this
+                        .running
+                        .push(Box::pin(fut.map(move |ret| (token, lineage, ret)))),
+                    Err(e) => state.finish(token, lineage, Err(e)),
+                }
+            }
+
+            if !state.output.is_empty() {
+                continue;
+            }
+
+            let (token, lineage, ret) = match this.running.poll_next_unpin(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => {
+                    state.skipped.items = state.queue.len();
+                    state.done = true;
+                    continue;
+                }
+                Poll::Ready(Some(next)) => next,
+            };
+            state.finish(token, lineage, ret);
+        }
+    }
+}
+
+impl<V: 'static, Q: WorkQueue<V>> Stream for WorkStream<V, Q> {
+    type Item = Result<V>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.poll_result(cx)
+            .map(|m| m.map(|ret| ret.map(|ret| ret.value)))
+    }
+}
+
+#[pin_project]
+pub struct TracedWorkStream<V, Q = MemoryQueue<V>> {
+    #[pin]
+    inner: WorkStream<V, Q>,
+}
+
+impl<V, Q> TracedWorkStream<V, Q> {
+    pub fn queue(&self) -> &Q {
+        self.inner.queue()
+    }
+
+    pub fn skipped(&self) -> &Skipped {
+        self.inner.skipped()
+    }
+
+    pub fn report(&self) -> ReportHandle {
+        self.inner.report()
+    }
+}
+
+impl<V: 'static, Q: WorkQueue<V>> Stream for TracedWorkStream<V, Q> {
+    type Item = Result<WorkResult<V>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.project().inner.poll_result(cx)
+    }
+}
@@ -1,64 +1,66 @@
 use super::futures_utils::{OneOfFuture, Promise};
+use super::spawner::{Spawner, ThreadSpawner};
 use super::{ConveyorError, Result, Station};
-use crossbeam;
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use futures::channel::oneshot::{channel, Receiver as FutureReceiver, Sender as FutureSender};
+use futures::lock::Mutex;
 use futures::prelude::*;
-use futures_old::{
-    sync::oneshot::{channel, Receiver as FutureReceiver, Sender as FutureSender},
-    Async, Future as OldFuture,
-};
 use std::pin::Pin;
-use std::task::{Poll, Context};
-use std::thread;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 
+/// Runs a synchronous function on `nwork` background workers. The workers
+/// stop once the station is dropped and the queued work is done.
+///
+/// Workers block, so they run with `Spawner::spawn_blocking_boxed` rather
+/// than as async tasks, e.g. on tokio's blocking pool.
 pub struct WorkStation<V: Send, O: Send> {
-    sx: crossbeam::channel::Sender<Option<(V, FutureSender<Result<O>>)>>,
-    txs: Option<Vec<thread::JoinHandle<()>>>,
+    sx: UnboundedSender<(V, FutureSender<Result<O>>)>,
 }
 
 impl<V: Send + 'static, O: Send + 'static> WorkStation<V, O> {
+    /// Runs every worker on a dedicated thread
     pub fn new<F, C: Send + 'static, Cinit>(nwork: usize, work: F, ctx: Cinit) -> WorkStation<V, O>
     where
         F: (Fn(V, &mut C) -> Result<O>) + Clone + Send + 'static,
         Cinit: (Fn() -> C),
     {
-        let mut workers = Vec::new();
-        let (sx, rx) = crossbeam::channel::bounded::<Option<(V, FutureSender<Result<O>>)>>(nwork);
+        WorkStation::with_spawner(&ThreadSpawner, nwork, work, ctx).expect("could not spawn worker")
+    }
+
+    pub fn with_spawner<S, F, C: Send + 'static, Cinit>(
+        spawner: &S,
+        nwork: usize,
+        work: F,
+        ctx: Cinit,
+    ) -> Result<WorkStation<V, O>>
+    where
+        S: Spawner + ?Sized,
+        F: (Fn(V, &mut C) -> Result<O>) + Clone + Send + 'static,
+        Cinit: (Fn() -> C),
+    {
+        let (sx, rx) = unbounded::<(V, FutureSender<Result<O>>)>();
+        let rx = Arc::new(Mutex::new(rx));
         for _i in 0..nwork {
             let w = work.clone();
             let mut c = ctx();
             let rxc = rx.clone();
 
-            workers.push(thread::spawn(move || loop {
-                let (ret, sx) = match rxc.recv() {
-                    Ok(ret) => match ret {
-                        Some(ret) => ret,
-                        None => {
-                            return;
-                        }
-                    },
-                    Err(_) => {
-                        return;
+            spawner.spawn_blocking_boxed(Box::new(move || {
+                futures::executor::block_on(async move {
+                    loop {
+                        let next = rxc.lock().await.next().await;
+                        let (ret, sx) = match next {
+                            Some(next) => next,
+                            None => return,
+                        };
+                        let _ = sx.send(w(ret, &mut c));
                     }
-                };
-                sx.send(w(ret, &mut c));
-            }));
+                })
+            }))?;
         }
 
-        WorkStation {
-            sx: sx,
-            txs: Some(workers),
-        }
-    }
-}
-
-impl<V: Send, O: Send> Drop for WorkStation<V, O> {
-    fn drop(&mut self) {
-        for _ in 0..self.txs.as_ref().unwrap().len() {
-            self.sx.send(None).unwrap();
-        }
-        for w in self.txs.take().unwrap().into_iter() {
-            w.join().expect("could not join handle");
-        }
+        Ok(WorkStation { sx })
     }
 }
 
@@ -69,7 +71,7 @@ impl<V: Send + 'static, O: Send + 'static> Station for WorkStation<V, O> {
 
     fn execute(&self, input: Self::Input) -> Self::Future {
         let (sx, rx) = channel();
-        match self.sx.send(Some((input, sx))) {
+        match self.sx.unbounded_send((input, sx)) {
             Ok(_) => OneOfFuture::new(Promise::First(WorkStationFuture { inner: rx })),
             Err(e) => OneOfFuture::new(Promise::Second(future::ready(Err(ConveyorError::new(e))))),
         }
@@ -82,15 +84,73 @@ pub struct WorkStationFuture<O: Send + 'static> {
 
 impl<O: Send + 'static> Future for WorkStationFuture<O> {
     type Output = Result<O>;
-    fn poll(self: Pin<&mut Self>, _waker: &mut Context) -> Poll<Self::Output> {
-        let this = unsafe { Pin::get_unchecked_mut(self) };
+    fn poll(self: Pin<&mut Self>, waker: &mut Context) -> Poll<Self::Output> {
+        match Pin::get_mut(self).inner.poll_unpin(waker) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(m)) => Poll::Ready(m),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(ConveyorError::new(e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::spawner::TestExecutor;
+    use super::*;
+
+    #[test]
+    fn work_station() {
+        let station = WorkStation::new(2, |v: i32, _ctx: &mut ()| Ok(v * 2), || ());
+        let ret = futures::executor::block_on(station.execute(21)).unwrap();
+        assert_eq!(ret, 42);
+    }
 
-        match this.inner.poll() {
-            Ok(m) => match m {
-                Async::NotReady => Poll::Pending,
-                Async::Ready(m) => Poll::Ready(m),
+    #[test]
+    fn work_station_spawner() {
+        let executor = TestExecutor::new();
+        let station = WorkStation::with_spawner(
+            &executor,
+            1,
+            |v: i32, ctx: &mut i32| {
+                *ctx += 1;
+                Ok(v + *ctx)
             },
-            Err(e) => Poll::Ready(Err(ConveyorError::new(e))),
-        }
+            || 0,
+        )
+        .unwrap();
+
+        // A single worker keeps its context across calls
+        let futures = (0..4).map(|i| station.execute(i * 10)).collect::<Vec<_>>();
+        let ret = executor
+            .block_on(future::join_all(futures))
+            .into_iter()
+            .map(|m| m.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ret, vec![1, 12, 23, 34]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn work_station_tokio() {
+        // blocking work mustn't stall the single thread of the runtime
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let (tx, rx) = std::sync::mpsc::channel::<i32>();
+        let rx = Arc::new(std::sync::Mutex::new(rx));
+        let station = WorkStation::with_spawner(
+            runtime.handle(),
+            1,
+            move |v: i32, _ctx: &mut ()| Ok(v + rx.lock().unwrap().recv().unwrap()),
+            || (),
+        )
+        .unwrap();
+
+        let ret = runtime.block_on(async move {
+            let ret = station.execute(40);
+            tokio::spawn(async move { tx.send(2).unwrap() });
+            ret.await
+        });
+        assert_eq!(ret.unwrap(), 42);
     }
 }